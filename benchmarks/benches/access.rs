@@ -3,7 +3,8 @@
 
 use cfg_if::cfg_if;
 use criterion::{Criterion, criterion_group, criterion_main};
-use threadid::{LiveThreadId, StdThreadId, UniqueThreadId};
+use threadid::debug::{self, DebugThreadId};
+use threadid::{CachedThreadId, CurrentThreadCache, IThreadId, LiveThreadId, StdThreadId, UniqueThreadId};
 
 fn std_current(c: &mut Criterion) {
     c.bench_function("std::thread::current().id()", |x| {
@@ -41,12 +42,92 @@ fn live_id_current(c: &mut Criterion) {
     });
 }
 
+/// The number of lookups performed by the loop benchmarks.
+const LOOP_COUNT: usize = 100;
+
+fn unique_id_current_loop(c: &mut Criterion) {
+    c.bench_function("threadid::UniqueThreadId::current() [loop]", |x| {
+        x.iter(|| {
+            for _ in 0..LOOP_COUNT {
+                let _ = std::hint::black_box(UniqueThreadId::current());
+            }
+        })
+    });
+}
+
+fn unique_id_cached_loop(c: &mut Criterion) {
+    c.bench_function("threadid::CurrentThreadCache::unique() [loop]", |x| {
+        x.iter(|| {
+            let cache = CurrentThreadCache::new();
+            for _ in 0..LOOP_COUNT {
+                let _ = std::hint::black_box(cache.unique());
+            }
+        })
+    });
+}
+
+fn generic_current_loop<T: IThreadId>(c: &mut Criterion, name: &str) {
+    c.bench_function(&format!("threadid::{name}::current() [generic loop]"), |x| {
+        x.iter(|| {
+            for _ in 0..LOOP_COUNT {
+                let _ = std::hint::black_box(T::current());
+            }
+        })
+    });
+    c.bench_function(&format!("threadid::CachedThreadId<{name}>::get() [loop]"), |x| {
+        x.iter(|| {
+            let cache = CachedThreadId::<T>::new();
+            for _ in 0..LOOP_COUNT {
+                let _ = std::hint::black_box(cache.get());
+            }
+        })
+    });
+}
+
+fn cached_generic_loop(c: &mut Criterion) {
+    generic_current_loop::<UniqueThreadId>(c, "UniqueThreadId");
+    generic_current_loop::<LiveThreadId>(c, "LiveThreadId");
+}
+
+fn all_ids_current(c: &mut Criterion) {
+    c.bench_function(
+        "threadid::{StdThreadId, UniqueThreadId, LiveThreadId}::current()",
+        |x| {
+            x.iter(|| {
+                (
+                    StdThreadId::current(),
+                    UniqueThreadId::current(),
+                    LiveThreadId::current(),
+                )
+            })
+        },
+    );
+}
+
+fn debug_id_current_name(c: &mut Criterion) {
+    c.bench_function("threadid::debug::DebugThreadId::current().name()", |x| {
+        x.iter(|| DebugThreadId::current().name().map(str::len))
+    });
+}
+
+fn debug_with_current_name(c: &mut Criterion) {
+    c.bench_function("threadid::debug::with_current_name()", |x| {
+        x.iter(|| debug::with_current_name(|name| name.map(str::len)))
+    });
+}
+
 criterion_group!(
     access,
     std_current,
     std_current_id,
     threadid_std_current,
     unique_id_current,
-    live_id_current
+    live_id_current,
+    unique_id_current_loop,
+    unique_id_cached_loop,
+    cached_generic_loop,
+    all_ids_current,
+    debug_id_current_name,
+    debug_with_current_name
 );
 criterion_main!(access);
@@ -0,0 +1,75 @@
+use std::sync::atomic::{AtomicU64, Ordering};
+
+use criterion::{Criterion, criterion_group, criterion_main};
+use threadid::{LiveThreadId, StdThreadId};
+
+/// The number of threads spawned simultaneously by each iteration.
+const STORM_SIZE: usize = 16;
+
+/// Spawn a storm of threads which each run the specified function.
+fn spawn_storm(func: impl Fn() + Sync) {
+    std::thread::scope(|scope| {
+        for _ in 0..STORM_SIZE {
+            scope.spawn(&func);
+        }
+    });
+}
+
+fn spawn_baseline(c: &mut Criterion) {
+    c.bench_function("thread storm [baseline]", |x| x.iter(|| spawn_storm(|| {})));
+}
+
+fn live_id_storm(c: &mut Criterion) {
+    c.bench_function("thread storm [LiveThreadId::current()]", |x| {
+        x.iter(|| {
+            spawn_storm(|| {
+                let _ = std::hint::black_box(LiveThreadId::current());
+            })
+        })
+    });
+}
+
+/// Measures the cold path of [`StdThreadId::current`], which runs once per thread.
+///
+/// Compare against a build with the `nightly` feature enabled,
+/// which reads the id directly instead of going through [`std::thread::current`].
+fn std_id_storm(c: &mut Criterion) {
+    c.bench_function("thread storm [StdThreadId::current()]", |x| {
+        x.iter(|| {
+            spawn_storm(|| {
+                let _ = std::hint::black_box(StdThreadId::current());
+            })
+        })
+    });
+}
+
+/// The number of increments performed by each thread in the counter benchmarks.
+const COUNTER_INCREMENTS: usize = 1000;
+
+/// Compares the strategies for incrementing the counter used to allocate a `UniqueThreadId`.
+fn counter_strategies(c: &mut Criterion) {
+    let counter = AtomicU64::new(1);
+    c.bench_function("counter fetch_update(AcqRel) [contended]", |x| {
+        x.iter(|| {
+            spawn_storm(|| {
+                for _ in 0..COUNTER_INCREMENTS {
+                    let _ = std::hint::black_box(
+                        counter.fetch_update(Ordering::AcqRel, Ordering::Relaxed, |old| old.checked_add(1)),
+                    );
+                }
+            })
+        })
+    });
+    c.bench_function("counter fetch_add(Relaxed) [contended]", |x| {
+        x.iter(|| {
+            spawn_storm(|| {
+                for _ in 0..COUNTER_INCREMENTS {
+                    let _ = std::hint::black_box(counter.fetch_add(1, Ordering::Relaxed));
+                }
+            })
+        })
+    });
+}
+
+criterion_group!(alloc, spawn_baseline, live_id_storm, std_id_storm, counter_strategies);
+criterion_main!(alloc);
@@ -0,0 +1,147 @@
+//! Defines [`CurrentThreadCache`] and [`CachedThreadId`].
+
+use core::cell::Cell;
+use core::marker::PhantomData;
+
+use crate::{IThreadId, UniqueThreadId};
+#[cfg(feature = "std")]
+use crate::{LiveThreadId, StdThreadId};
+
+/// Caches the ids of the current thread for the duration of a scope.
+///
+/// Each id is looked up the first time it is requested,
+/// and then returned from the cache on subsequent requests.
+/// This amortizes the (already cheap) thread-local lookup across many uses,
+/// which can be useful in a tight loop where the optimizer is unable to hoist the lookup itself.
+///
+/// This type is neither [`Send`] nor [`Sync`],
+/// so the cached ids always belong to the thread which uses the cache.
+/// The cache should be short-lived, created within a single scope and dropped at its end.
+/// Since a [`LiveThreadId`] is only valid while its thread is alive,
+/// it is always safe to use the cached value for the duration of the scope.
+///
+/// ```
+/// use threadid::CurrentThreadCache;
+/// let cache = CurrentThreadCache::new();
+/// for _ in 0..16 {
+///     assert_eq!(cache.unique(), threadid::UniqueThreadId::current());
+/// }
+/// ```
+#[derive(Debug, Default)]
+pub struct CurrentThreadCache {
+    unique: Cell<Option<UniqueThreadId>>,
+    #[cfg(feature = "std")]
+    live: Cell<Option<LiveThreadId>>,
+    #[cfg(feature = "std")]
+    std: Cell<Option<StdThreadId>>,
+    /// Ensures the cache can't be sent to another thread
+    _marker: PhantomData<*const ()>,
+}
+impl CurrentThreadCache {
+    /// Create a new cache, without looking up any ids.
+    #[inline]
+    #[must_use]
+    pub const fn new() -> Self {
+        CurrentThreadCache {
+            unique: Cell::new(None),
+            #[cfg(feature = "std")]
+            live: Cell::new(None),
+            #[cfg(feature = "std")]
+            std: Cell::new(None),
+            _marker: PhantomData,
+        }
+    }
+
+    /// Get the [`UniqueThreadId`] of the current thread.
+    #[inline]
+    pub fn unique(&self) -> UniqueThreadId {
+        Self::get_or_lookup(&self.unique, UniqueThreadId::current)
+    }
+
+    /// Get the [`LiveThreadId`] of the current thread.
+    #[cfg(feature = "std")]
+    #[cfg_attr(feature = "nightly-docs", doc(cfg(feature = "std")))]
+    #[inline]
+    pub fn live(&self) -> LiveThreadId {
+        Self::get_or_lookup(&self.live, LiveThreadId::current)
+    }
+
+    /// Get the [`StdThreadId`] of the current thread.
+    #[cfg(feature = "std")]
+    #[cfg_attr(feature = "nightly-docs", doc(cfg(feature = "std")))]
+    #[inline]
+    pub fn std(&self) -> StdThreadId {
+        Self::get_or_lookup(&self.std, StdThreadId::current)
+    }
+
+    #[inline]
+    fn get_or_lookup<T: Copy>(cell: &Cell<Option<T>>, lookup: impl FnOnce() -> T) -> T {
+        match cell.get() {
+            Some(existing) => existing,
+            None => {
+                let id = lookup();
+                cell.set(Some(id));
+                id
+            }
+        }
+    }
+}
+
+/// Caches a single id of the current thread for the duration of a scope, for generic code.
+///
+/// This is the generic equivalent of [`CurrentThreadCache`],
+/// making the "fetch once" pattern explicit for code which is parameterized over an [`IThreadId`].
+/// The id is looked up by [`IThreadId::current`] on the first call to [`CachedThreadId::get`],
+/// and subsequent calls only read the cache.
+///
+/// Every id type already caches its value in a thread-local after the first lookup,
+/// so calling [`IThreadId::current`] repeatedly never requires synchronization.
+/// Caching the id in a local avoids repeating the thread-local access itself.
+///
+/// This type is neither [`Send`] nor [`Sync`],
+/// so the cached id always belongs to the thread which uses the cache.
+///
+/// ```
+/// use threadid::{CachedThreadId, IThreadId, UniqueThreadId};
+/// fn count_owned<T: IThreadId>(owners: &[T]) -> usize {
+///     let current = CachedThreadId::<T>::new();
+///     owners.iter().filter(|&&owner| owner == current.get()).count()
+/// }
+/// assert_eq!(count_owned(&[UniqueThreadId::current(); 4]), 4);
+/// ```
+pub struct CachedThreadId<T: IThreadId> {
+    id: Cell<Option<T>>,
+    /// Ensures the cache can't be sent to another thread
+    _marker: PhantomData<*const ()>,
+}
+impl<T: IThreadId> CachedThreadId<T> {
+    /// Create a new cache, without looking up the id.
+    #[inline]
+    #[must_use]
+    pub const fn new() -> Self {
+        CachedThreadId {
+            id: Cell::new(None),
+            _marker: PhantomData,
+        }
+    }
+
+    /// Get the id of the current thread.
+    ///
+    /// May panic if the id is first requested from a thread destructor,
+    /// as described by [`IThreadId::current`].
+    #[inline]
+    pub fn get(&self) -> T {
+        CurrentThreadCache::get_or_lookup(&self.id, T::current)
+    }
+}
+impl<T: IThreadId> Default for CachedThreadId<T> {
+    #[inline]
+    fn default() -> Self {
+        CachedThreadId::new()
+    }
+}
+impl<T: IThreadId> core::fmt::Debug for CachedThreadId<T> {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.debug_tuple("CachedThreadId").field(&self.id.get()).finish()
+    }
+}
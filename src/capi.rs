@@ -0,0 +1,52 @@
+//! Exposes the current thread ids through a C ABI.
+//!
+//! The ids are process-local, and are only meaningful within the same execution of the program.
+//! If the library is linked multiple times, each copy has its own ids.
+//!
+//! Panics cannot unwind across the C ABI, so any panic aborts the process instead.
+
+use core::mem::{align_of, size_of};
+
+use crate::{LiveThreadId, UniqueThreadId};
+
+/// The version of the C ABI exposed by this module.
+///
+/// This is bumped whenever the signature of an exported function changes,
+/// or the layout of a type passed across the C ABI changes.
+/// Adding new functions does not change the version.
+/// A host can compare the result of [`threadid_abi_version`] against the version it was built for,
+/// to detect a mismatch at load time.
+pub const THREADID_ABI_VERSION: u32 = 1;
+
+// The ids are passed across the C ABI as their integer values, which must have the same layout.
+const _: () = assert!(size_of::<UniqueThreadId>() == size_of::<u64>());
+const _: () = assert!(align_of::<UniqueThreadId>() == align_of::<u64>());
+const _: () = assert!(size_of::<LiveThreadId>() == size_of::<usize>());
+const _: () = assert!(align_of::<LiveThreadId>() == align_of::<usize>());
+
+/// Get the version of the C ABI, equal to [`THREADID_ABI_VERSION`].
+#[no_mangle]
+pub extern "C" fn threadid_abi_version() -> u32 {
+    THREADID_ABI_VERSION
+}
+
+/// Get the integer value of the [`UniqueThreadId`] of the current thread.
+///
+/// This is equivalent to `UniqueThreadId::current().to_int()`.
+#[no_mangle]
+pub extern "C" fn threadid_unique_current() -> u64 {
+    abort_on_panic(|| UniqueThreadId::current().to_int())
+}
+
+/// Get the integer value of the [`LiveThreadId`] of the current thread.
+///
+/// This is equivalent to `LiveThreadId::current().to_int()`.
+#[no_mangle]
+pub extern "C" fn threadid_live_current() -> usize {
+    abort_on_panic(|| LiveThreadId::current().to_int())
+}
+
+#[inline]
+fn abort_on_panic<R>(func: impl FnOnce() -> R) -> R {
+    std::panic::catch_unwind(core::panic::AssertUnwindSafe(func)).unwrap_or_else(|_| std::process::abort())
+}
@@ -1,21 +1,152 @@
 //! Identifies a thread in a form useful for debugging.
+//!
+//! Without the `std` feature, threads have no names unless one is registered with [`set_current_name`].
 
+#[cfg(feature = "alloc")]
+use alloc::sync::Arc;
+use core::cell::Cell;
+#[cfg(feature = "std")]
+use core::cell::RefCell;
 use core::fmt::{Debug, Display, Formatter};
 
+#[cfg(feature = "std")]
+use crate::LiveThreadId;
 use crate::UniqueThreadId;
+#[cfg(feature = "std")]
+use crate::live::VersionedLiveThreadId;
+
+fast_thread_local! {
+    static CURRENT_ROLE: Cell<Option<&'static str>> = Cell::new(None);
+    static CURRENT_NAME: Cell<Option<&'static str>> = Cell::new(None);
+}
+#[cfg(feature = "std")]
+std::thread_local! {
+    /// The handle of the current thread, cached to access its name.
+    static CURRENT_THREAD: RefCell<Option<std::thread::Thread>> = const { RefCell::new(None) };
+}
+/// The size of the thread-locals declared by this module.
+#[cfg(feature = "std")]
+pub(crate) const LOCALS_SIZE: usize = 2 * core::mem::size_of::<Cell<Option<&'static str>>>()
+    + core::mem::size_of::<RefCell<Option<std::thread::Thread>>>();
+/// The size of the thread-locals declared by this module.
+#[cfg(not(feature = "std"))]
+pub(crate) const LOCALS_SIZE: usize = 2 * core::mem::size_of::<Cell<Option<&'static str>>>();
+
+/// Register a name for the current thread, used by debug ids created afterwards.
+///
+/// This is primarily intended for `no_std` targets, where threads otherwise have no names.
+/// If the `std` feature is enabled, the registered name takes precedence over [`std::thread::Thread::name`].
+/// It only applies to the current thread.
+pub fn set_current_name(name: &'static str) {
+    CURRENT_NAME.with(|cell| cell.set(Some(name)));
+}
+
+/// Clear the name of the current thread, previously registered by [`set_current_name`].
+pub fn clear_current_name() {
+    CURRENT_NAME.with(|cell| cell.set(None));
+}
+
+/// Set the role of the current thread, such as `"io"` or `"compute"`.
+///
+/// The role is a program-assigned category which complements the name of the thread,
+/// and is appended to the [`Display`] output of a [`DebugThreadId`].
+/// It only applies to the current thread, and only affects [`DebugThreadId`]s created afterwards.
+/// By default, threads have no role.
+pub fn set_current_role(role: &'static str) {
+    CURRENT_ROLE.with(|cell| cell.set(Some(role)));
+}
+
+/// Clear the role of the current thread, previously set by [`set_current_role`].
+pub fn clear_current_role() {
+    CURRENT_ROLE.with(|cell| cell.set(None));
+}
+
+/// Access the name of the current thread according to the standard library, or `None` if it is unnamed.
+///
+/// The handle of the thread is cached on first access,
+/// so subsequent calls avoid the [`Arc::clone`] performed by [`std::thread::current`].
+/// This includes caching the absence of a name.
+/// If the cache has already been destroyed during thread teardown,
+/// falls back to calling [`std::thread::current`].
+///
+/// The name is only borrowed for the duration of the callback,
+/// because the cache is released once the thread exits.
+#[cfg(feature = "std")]
+#[cfg_attr(feature = "nightly-docs", doc(cfg(feature = "std")))]
+#[allow(clippy::missing_panics_doc)] // the callback is only taken once
+pub fn with_current_name<R>(func: impl FnOnce(Option<&str>) -> R) -> R {
+    let mut func = Some(func);
+    let res = CURRENT_THREAD.try_with(|cell| {
+        if cell.borrow().is_none() {
+            let thread = std::thread::current();
+            *cell.borrow_mut() = Some(thread);
+        }
+        let thread = cell.borrow();
+        (func.take().unwrap())(thread.as_ref().and_then(std::thread::Thread::name))
+    });
+    match res {
+        Ok(res) => res,
+        Err(_) => (func.take().unwrap())(std::thread::current().name()),
+    }
+}
 
 /// Identifies a thread in a form useful for debugging.
 ///
 /// Uses the [name] if possible and the id where it is not.
+/// A name registered by [`set_current_name`] takes precedence.
+///
+/// Comparisons, ordering, and hashing only use the [`UniqueThreadId`], which is the identity of the thread.
+/// Two [`DebugThreadId`]s with the same id compare equal even if their names or roles differ,
+/// for example if one was created before the role of the thread was set.
 ///
-/// [name]: std::thread::Thread::name
+/// [name]: ::std::thread::Thread::name
 #[derive(Clone)]
 #[must_use]
 pub struct DebugThreadId {
+    info: ThreadInfo,
+    id: UniqueThreadId,
+    role: Option<&'static str>,
+}
+/// The source of the name of a [`DebugThreadId`].
+#[derive(Clone)]
+enum ThreadInfo {
     /// This is really an `Arc<ThreadInfo>`,
     /// so it is cheap to Clone and fine if it lives beyond thread death
-    info: std::thread::Thread,
-    id: UniqueThreadId,
+    #[cfg(feature = "std")]
+    Thread(std::thread::Thread),
+    /// A name reconstructed by [`DebugThreadId::from_parts`].
+    #[cfg(feature = "alloc")]
+    Owned(Option<Arc<str>>),
+    /// A name registered by [`set_current_name`], or `None` if no name is available.
+    Registered(Option<&'static str>),
+}
+impl ThreadInfo {
+    /// Get the source of the name of the current thread.
+    fn current() -> ThreadInfo {
+        match CURRENT_NAME.with(Cell::get) {
+            Some(name) => ThreadInfo::Registered(Some(name)),
+            None => {
+                cfg_if::cfg_if! {
+                    if #[cfg(feature = "std")] {
+                        ThreadInfo::Thread(std::thread::current())
+                    } else {
+                        ThreadInfo::Registered(None)
+                    }
+                }
+            }
+        }
+    }
+
+    #[inline]
+    fn name(&self) -> Option<&'_ str> {
+        match *self {
+            #[cfg(feature = "std")]
+            ThreadInfo::Thread(ref thread) => thread.name(),
+            #[cfg(feature = "alloc")]
+            ThreadInfo::Owned(ref name) => name.as_deref(),
+            ThreadInfo::Registered(name) => name,
+        }
+    }
 }
 impl DebugThreadId {
     /// Get the [`DebugThreadId`] of the current thread.
@@ -24,8 +155,25 @@ impl DebugThreadId {
     /// due to the need to fetch the thread's name.
     pub fn current() -> DebugThreadId {
         DebugThreadId {
-            info: std::thread::current(),
+            info: ThreadInfo::current(),
             id: UniqueThreadId::current(),
+            role: CURRENT_ROLE.with(Cell::get),
+        }
+    }
+
+    /// Reconstruct a [`DebugThreadId`] from its id and name,
+    /// for example when replaying stored logs.
+    ///
+    /// The thread does not need to be alive, and the name is copied.
+    /// The [`Display`], [`Debug`] and serde output matches the id of the original thread,
+    /// except that the role of the thread is not included.
+    #[cfg(feature = "alloc")]
+    #[cfg_attr(feature = "nightly-docs", doc(cfg(feature = "alloc")))]
+    pub fn from_parts(id: UniqueThreadId, name: Option<&str>) -> DebugThreadId {
+        DebugThreadId {
+            info: ThreadInfo::Owned(name.map(Arc::from)),
+            id,
+            role: None,
         }
     }
 
@@ -41,6 +189,85 @@ impl DebugThreadId {
     pub fn id(&self) -> UniqueThreadId {
         self.id
     }
+
+    /// Get the role of the thread, or `None` if it has no role.
+    ///
+    /// See [`set_current_role`] for details.
+    #[inline]
+    #[must_use]
+    pub fn role(&self) -> Option<&'static str> {
+        self.role
+    }
+
+    /// Display this id using the specified layout,
+    /// instead of the default layout used by the [`Display`] implementation.
+    ///
+    /// ```
+    /// use threadid::debug::{DebugThreadId, DisplayStyle};
+    /// let id = DebugThreadId::current();
+    /// assert_eq!(id.display_with(DisplayStyle::IdOnly).to_string(), id.id().to_int().to_string());
+    /// ```
+    #[inline]
+    #[must_use]
+    pub fn display_with(&self, style: DisplayStyle) -> impl Display + '_ {
+        StyledDisplay { id: self, style }
+    }
+}
+/// A layout for displaying a [`DebugThreadId`], used by [`DebugThreadId::display_with`].
+///
+/// Unlike the default [`Display`] implementation, these layouts never include the role of the thread.
+/// If the thread has no name, all layouts display just the id.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum DisplayStyle {
+    /// Display only the id, as in `123`.
+    IdOnly,
+    /// Display only the name, as in `main`.
+    NameOnly,
+    /// Display the name followed by the id, as in `main#123`.
+    NameThenId,
+    /// Display the id followed by the quoted name, as in `123("main")`.
+    IdThenName,
+}
+struct StyledDisplay<'a> {
+    id: &'a DebugThreadId,
+    style: DisplayStyle,
+}
+impl Display for StyledDisplay<'_> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> core::fmt::Result {
+        let id = self.id.id.to_int();
+        match (self.style, self.id.name()) {
+            (DisplayStyle::IdOnly, _) | (_, None) => write!(f, "{id}"),
+            (DisplayStyle::NameOnly, Some(name)) => f.write_str(name),
+            (DisplayStyle::NameThenId, Some(name)) => write!(f, "{name}#{id}"),
+            (DisplayStyle::IdThenName, Some(name)) => write!(f, "{id}({name:?})"),
+        }
+    }
+}
+impl PartialEq for DebugThreadId {
+    #[inline]
+    fn eq(&self, other: &Self) -> bool {
+        self.id == other.id
+    }
+}
+impl Eq for DebugThreadId {}
+impl PartialOrd for DebugThreadId {
+    #[inline]
+    fn partial_cmp(&self, other: &Self) -> Option<core::cmp::Ordering> {
+        Some(self.cmp(other))
+    }
+}
+impl Ord for DebugThreadId {
+    #[inline]
+    fn cmp(&self, other: &Self) -> core::cmp::Ordering {
+        self.id.cmp(&other.id)
+    }
+}
+impl core::hash::Hash for DebugThreadId {
+    #[inline]
+    fn hash<H: core::hash::Hasher>(&self, state: &mut H) {
+        self.id.hash(state);
+    }
 }
 impl Display for DebugThreadId {
     fn fmt(&self, f: &mut Formatter<'_>) -> core::fmt::Result {
@@ -48,6 +275,9 @@ impl Display for DebugThreadId {
         if let Some(name) = self.name() {
             write!(f, "({name:?})")?;
         }
+        if let Some(role) = self.role {
+            write!(f, "[{role}]")?;
+        }
         Ok(())
     }
 }
@@ -68,6 +298,8 @@ impl slog::Value for DebugThreadId {
         serializer.emit_arguments(key, &format_args!("{self}"))
     }
 }
+/// Serializes a struct containing the name and id for human-readable formats,
+/// and only the integer id for compact formats.
 #[cfg(feature = "serde")]
 impl serde::Serialize for DebugThreadId {
     fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
@@ -75,6 +307,9 @@ impl serde::Serialize for DebugThreadId {
         S: serde::Serializer,
     {
         use serde::ser::SerializeStruct;
+        if !serializer.is_human_readable() {
+            return serde::Serialize::serialize(&self.id(), serializer);
+        }
         let name = self.name();
         let mut ser = serializer.serialize_struct("ThreadDebugId", if name.is_some() { 2 } else { 1 })?;
         if let Some(name) = name {
@@ -86,3 +321,122 @@ impl serde::Serialize for DebugThreadId {
         ser.end()
     }
 }
+
+/// Identifies a thread in a form useful for debugging, using its [`LiveThreadId`].
+///
+/// This is equivalent to a [`DebugThreadId`],
+/// except that the numeric portion is the dense [`LiveThreadId`] instead of a [`UniqueThreadId`].
+/// This is useful when debugging data structures indexed by [`LiveThreadId::to_int`].
+/// Since live ids are reused once a thread dies,
+/// the numeric portion may refer to different threads over time.
+#[cfg(feature = "std")]
+#[cfg_attr(feature = "nightly-docs", doc(cfg(feature = "std")))]
+#[derive(Clone)]
+#[must_use]
+pub struct DebugLiveThreadId {
+    info: ThreadInfo,
+    id: VersionedLiveThreadId,
+    role: Option<&'static str>,
+}
+#[cfg(feature = "std")]
+impl DebugLiveThreadId {
+    /// Get the [`DebugLiveThreadId`] of the current thread.
+    ///
+    /// Will be significantly slower than [`LiveThreadId::current`],
+    /// due to the need to fetch the thread's name.
+    pub fn current() -> DebugLiveThreadId {
+        DebugLiveThreadId {
+            info: ThreadInfo::current(),
+            id: VersionedLiveThreadId::current(),
+            role: CURRENT_ROLE.with(Cell::get),
+        }
+    }
+
+    /// Get the name of the thread, or `None` if not available.
+    #[inline]
+    #[must_use]
+    pub fn name(&self) -> Option<&'_ str> {
+        self.info.name()
+    }
+
+    /// Get the id of this thread as a [`LiveThreadId`].
+    #[inline]
+    pub fn id(&self) -> LiveThreadId {
+        self.id.id()
+    }
+
+    /// Check if the thread this id refers to is still running.
+    ///
+    /// This uses [`VersionedLiveThreadId::is_stale`],
+    /// so it requires acquiring the lock on the allocator.
+    /// The result is a racy snapshot, because the thread could die right afterwards.
+    /// Once it returns `false`, it will never return `true` again,
+    /// even if the [`LiveThreadId`] is reused by another thread.
+    #[must_use]
+    pub fn is_alive(&self) -> bool {
+        !self.id.is_stale()
+    }
+
+    /// Get the role of the thread, or `None` if it has no role.
+    ///
+    /// See [`set_current_role`] for details.
+    #[inline]
+    #[must_use]
+    pub fn role(&self) -> Option<&'static str> {
+        self.role
+    }
+}
+#[cfg(feature = "std")]
+impl Display for DebugLiveThreadId {
+    fn fmt(&self, f: &mut Formatter<'_>) -> core::fmt::Result {
+        write!(f, "{}", self.id.index())?;
+        if let Some(name) = self.name() {
+            write!(f, "({name:?})")?;
+        }
+        if let Some(role) = self.role {
+            write!(f, "[{role}]")?;
+        }
+        Ok(())
+    }
+}
+#[cfg(feature = "std")]
+impl Debug for DebugLiveThreadId {
+    fn fmt(&self, f: &mut Formatter<'_>) -> core::fmt::Result {
+        write!(f, "LiveThreadId({}", self.id.index())?;
+        if let Some(name) = self.name() {
+            write!(f, ", {name:?})")?;
+        } else {
+            f.write_str(")")?;
+        }
+        Ok(())
+    }
+}
+#[cfg(all(feature = "std", feature = "slog"))]
+impl slog::Value for DebugLiveThreadId {
+    fn serialize(&self, _record: &slog::Record, key: slog::Key, serializer: &mut dyn slog::Serializer) -> slog::Result {
+        serializer.emit_arguments(key, &format_args!("{self}"))
+    }
+}
+/// Serializes a struct containing the name and id for human-readable formats,
+/// and only the integer id for compact formats.
+#[cfg(all(feature = "std", feature = "serde"))]
+impl serde::Serialize for DebugLiveThreadId {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: serde::Serializer,
+    {
+        use serde::ser::SerializeStruct;
+        if !serializer.is_human_readable() {
+            return serde::Serialize::serialize(&self.id(), serializer);
+        }
+        let name = self.name();
+        let mut ser = serializer.serialize_struct("DebugLiveThreadId", if name.is_some() { 2 } else { 1 })?;
+        if let Some(name) = name {
+            ser.serialize_field("name", &name)?;
+        } else {
+            ser.skip_field("name")?;
+        }
+        ser.serialize_field("id", &self.id())?;
+        ser.end()
+    }
+}
@@ -13,6 +13,15 @@
 //! Using [`debug::DebugThreadId`] is a convenience wrapper which displays [`std::thread::Thread::name`](::std::thread::Thread::name)
 //! where possible, in addition to using [`UniqueThreadId`] as a fallback when the thread is unnamed.
 //!
+//! # Targets without atomics
+//! Ids are allocated using [`portable_atomic`](https://docs.rs/portable-atomic/1),
+//! which requires compare-and-swap operations.
+//! On targets which lack them, such as single-core `thumbv6m-none-eabi` microcontrollers,
+//! enable the `critical-section` feature of this crate,
+//! and provide an implementation of the [`critical-section`](https://docs.rs/critical-section/1) crate.
+//! Without the `std` feature, the `nightly` feature is required to declare thread-locals.
+//! The `alloc` feature is not supported on these targets, because [`alloc::sync::Arc`](https://doc.rust-lang.org/stable/alloc/sync/struct.Arc.html) requires atomic pointers.
+//!
 //! # Similar Crates
 //! The crate name and functionality is similar to that of the `thread-id` crate,
 //! which simply wraps OS primitives (pthreads/windows).
@@ -60,6 +69,7 @@ extern crate alloc;
 use core::fmt::Debug;
 use core::hash::Hash;
 
+pub use cache::{CachedThreadId, CurrentThreadCache};
 #[cfg(feature = "std")]
 #[cfg_attr(feature = "nightly-docs", doc(cfg(feature = "std")))]
 pub use live::LiveThreadId;
@@ -69,18 +79,23 @@ pub use unique::UniqueThreadId;
 #[cfg_attr(feature = "nightly-docs", doc(cfg(feature = "std")))]
 pub use self::std::StdThreadId;
 
-#[macro_use]
-mod utils;
 #[macro_use]
 mod locals;
-#[cfg(feature = "std")]
-#[cfg_attr(feature = "nightly-docs", doc(cfg(feature = "std")))]
+#[macro_use]
+mod utils;
+mod cache;
+#[cfg(feature = "capi")]
+#[cfg_attr(feature = "nightly-docs", doc(cfg(feature = "capi")))]
+pub mod capi;
 pub mod debug;
 #[cfg(feature = "std")]
 #[cfg_attr(feature = "nightly-docs", doc(cfg(feature = "std")))]
 pub mod live;
 #[cfg(feature = "std")]
 #[cfg_attr(feature = "nightly-docs", doc(cfg(feature = "std")))]
+pub mod namespace;
+#[cfg(feature = "std")]
+#[cfg_attr(feature = "nightly-docs", doc(cfg(feature = "std")))]
 pub mod std;
 pub mod unique;
 
@@ -90,15 +105,99 @@ pub mod unique;
 /// Ids are guaranteed to differ across currently live threads for [`LiveThreadId`],
 /// and among all threads that have ever existed for [`UniqueThreadId`] and [`StdThreadId`].
 pub unsafe trait IThreadId: Copy + Eq + Hash + Debug + sealed::Sealed {
+    /// The underlying representation of this id, which has a niche.
+    ///
+    /// This allows generic code to store `Option<Self::Raw>` in the same space as `Self::Raw`.
+    ///
+    /// - For a [`UniqueThreadId`] this is a [`NonZeroU64`](core::num::NonZeroU64), where zero is the niche.
+    /// - For a [`CompactThreadId`](unique::CompactThreadId) this is a [`NonZeroU32`](core::num::NonZeroU32).
+    /// - For a [`LiveThreadId`] this is a [`nonmax::NonMaxUsize`], where [`usize::MAX`] is the niche.
+    /// - For a [`StdThreadId`] this is a [`std::thread::ThreadId`](::std::thread::ThreadId).
+    ///   The standard library does not document a niche for it,
+    ///   so the size of `Option<ThreadId>` is not guaranteed.
+    type Raw: Copy + Eq + Hash + Debug;
+
     /// Get the id of the currently executing thread.
     ///
     /// May panic if called from a thread destructor.
     fn current() -> Self;
+
+    /// Get the id of the currently executing thread as its [raw representation](Self::Raw).
+    ///
+    /// May panic if called from a thread destructor.
+    #[inline]
+    #[must_use]
+    fn current_raw() -> Self::Raw {
+        Self::current().to_raw()
+    }
+
+    /// Convert this id into its [raw representation](Self::Raw).
+    fn to_raw(self) -> Self::Raw;
+    /// Get the id of the currently executing thread,
+    /// returning an error instead of panicking if it cannot be accessed.
+    ///
+    /// This is useful in [`Drop`] implementations which may run during thread teardown.
+    /// Only [`LiveThreadId`] can fail during teardown,
+    /// because it releases its id when the thread is destroyed.
+    ///
+    /// ## Errors
+    /// Returns an error if the thread is being torn down and the id is no longer accessible,
+    /// or if no more ids can be allocated.
+    fn try_current() -> Result<Self, AccessError>;
+
+    /// Convert this id into a non-zero integer, allowing generic code to use the niche uniformly.
+    ///
+    /// For a [`UniqueThreadId`] this is [`UniqueThreadId::to_int`].
+    /// For a [`LiveThreadId`] this is one more than [`LiveThreadId::to_int`],
+    /// because live ids can be zero, so subtracting one recovers the index.
+    /// For a [`StdThreadId`] this requires the `nightly` feature,
+    /// because the integer value of a [`std::thread::ThreadId`](::std::thread::ThreadId) is otherwise inaccessible.
+    ///
+    /// Returns `None` if the integer value is not available.
+    fn to_nonzero_u128(self) -> Option<core::num::NonZeroU128>;
+
+    /// Get the integer value of this id.
+    ///
+    /// For a [`UniqueThreadId`] or [`LiveThreadId`] this is the value of `to_int`.
+    ///
+    /// For a [`StdThreadId`] this is the value of `std::thread::ThreadId::as_u64`
+    /// if the `nightly` feature is enabled.
+    /// Otherwise it is derived from the [`Hash`] implementation of [`std::thread::ThreadId`](::std::thread::ThreadId),
+    /// which currently hashes exactly its integer value.
+    /// If the standard library changes its implementation, the value becomes a hash of the id,
+    /// and may no longer be unique.
+    fn as_u64(&self) -> u64;
+}
+
+/// The error returned when the id of the current thread cannot be accessed or allocated.
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Hash)]
+#[non_exhaustive]
+pub enum AccessError {
+    /// The thread is being torn down, and the thread-local state holding its id has been destroyed.
+    ///
+    /// For a [`LiveThreadId`], this means the id has already been released for reuse.
+    Destroyed,
+    /// All possible ids have been exhausted, so no id could be allocated for the thread.
+    ///
+    /// See [`unique::ThreadIdOverflow`].
+    Overflow,
+}
+impl core::fmt::Display for AccessError {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match *self {
+            AccessError::Destroyed => f.write_str("thread id accessed after thread destruction"),
+            AccessError::Overflow => f.write_str("thread id overflow"),
+        }
+    }
 }
+#[cfg(feature = "std")]
+#[cfg_attr(feature = "nightly-docs", doc(cfg(feature = "std")))]
+impl ::std::error::Error for AccessError {}
 
 mod sealed {
     pub trait Sealed {}
     impl Sealed for crate::UniqueThreadId {}
+    impl Sealed for crate::unique::CompactThreadId {}
     #[cfg(feature = "std")]
     impl Sealed for crate::LiveThreadId {}
     #[cfg(feature = "std")]
@@ -115,3 +214,128 @@ mod sealed {
 pub fn current<T: IThreadId>() -> T {
     T::current()
 }
+
+/// Get the index of the current thread, as given by its [`LiveThreadId`].
+///
+/// Convenience method for calling [`LiveThreadId::current`] followed by [`LiveThreadId::to_int`],
+/// useful for indexing a vector.
+#[cfg(feature = "std")]
+#[cfg_attr(feature = "nightly-docs", doc(cfg(feature = "std")))]
+#[inline]
+#[must_use]
+pub fn current_index() -> usize {
+    LiveThreadId::current().to_int()
+}
+
+/// Get a human-readable id for the current thread, including its name where possible.
+///
+/// Convenience method for calling [`debug::DebugThreadId::current`].
+#[inline]
+pub fn current_debug() -> debug::DebugThreadId {
+    debug::DebugThreadId::current()
+}
+
+fast_thread_local! {
+    static SEQUENCE: core::cell::Cell<u64> = core::cell::Cell::new(0);
+}
+
+/// Get the next number in a monotonically increasing sequence specific to the current thread.
+///
+/// This is useful for ordering events within a single thread.
+/// Each thread has an independent sequence starting from zero,
+/// so numbers from different threads are not comparable.
+/// The sequence is stored in a thread-local [`Cell`](core::cell::Cell) and does not use atomics.
+/// It is never reset during the lifetime of a thread.
+#[inline]
+#[must_use]
+pub fn current_seq() -> u64 {
+    SEQUENCE.with(|cell| {
+        let seq = cell.get();
+        cell.set(seq + 1);
+        seq
+    })
+}
+
+/// The number of bytes used by the thread-local state of this crate, for each thread.
+///
+/// This is the sum of the sizes of the thread-locals declared by this crate,
+/// which depends on the enabled features:
+/// - The sequence used by [`current_seq`] is always counted.
+/// - The role and registered name used by [`debug`] are always counted.
+/// - The cached [`UniqueThreadId`] is counted unless the `nightly` and `std` features are enabled,
+///   in which case it is derived from the standard library's id.
+/// - The cached [`unique::CompactThreadId`] is always counted.
+/// - The cached [`StdThreadId`] is counted if the `std` feature is enabled without the `nightly` feature.
+/// - The cached [`LiveThreadId`] and the guard which releases it are counted if the `std` feature is enabled,
+///   along with the cached thread handle used by [`debug`] and the list of namespaces used by [`namespace`].
+/// - The flag used to detect recursive allocation is counted in debug builds.
+///
+/// It does not include any bookkeeping by the standard library,
+/// nor the heap allocations made for each [`namespace::ThreadIdNamespace`] used by a thread.
+/// Thread-locals are only initialized once used,
+/// although many platforms reserve space for them when a thread is created.
+pub const PER_THREAD_OVERHEAD: usize = core::mem::size_of::<core::cell::Cell<u64>>()
+    + utils::LOCALS_SIZE
+    + unique::LOCALS_SIZE
+    + debug::LOCALS_SIZE
+    + STD_LOCALS_SIZE;
+/// The size of the thread-locals which require the `std` feature.
+#[cfg(feature = "std")]
+const STD_LOCALS_SIZE: usize = self::std::LOCALS_SIZE + live::LOCALS_SIZE + namespace::LOCALS_SIZE;
+/// The size of the thread-locals which require the `std` feature.
+#[cfg(not(feature = "std"))]
+const STD_LOCALS_SIZE: usize = 0;
+
+/// Get the number of bytes used by the thread-local state of this crate, for each thread.
+///
+/// Equivalent to [`PER_THREAD_OVERHEAD`].
+#[inline]
+#[must_use]
+pub const fn per_thread_overhead() -> usize {
+    PER_THREAD_OVERHEAD
+}
+
+/// Identifies the instance of this crate which is linked into the current call site.
+///
+/// If multiple versions of this crate are linked into a program,
+/// each has its own global state, so ids from one instance are unrelated to ids from another.
+/// Comparing the fingerprints captured through two different dependency paths
+/// detects whether they go through the same instance.
+///
+/// The fingerprint is derived from the address of a private static,
+/// so it is stable for the duration of the program, but differs between executions.
+/// It only detects duplicate instances, it does not prevent them.
+#[inline]
+#[must_use]
+pub fn instance_fingerprint() -> u64 {
+    /// A static with a unique address for each instance of this crate.
+    static ANCHOR: u8 = 0;
+    core::ptr::addr_of!(ANCHOR) as usize as u64
+}
+
+/// Reset all global state of this library, as if no ids had ever been allocated.
+///
+/// This is intended to make tests deterministic and independent of each other.
+/// It resets the counter used by [`UniqueThreadId`], the allocator used by [`LiveThreadId`],
+/// and clears the ids cached by the current thread.
+/// If [`UniqueThreadId`] wraps [`std::thread::ThreadId`](::std::thread::ThreadId), its ids cannot be reset.
+/// Ids from a [`namespace::ThreadIdNamespace`] are not affected.
+///
+/// ## Safety
+/// No other threads which have used this library may be running.
+/// Otherwise, their ids would be handed out again, breaking the guarantees of [`IThreadId`].
+///
+/// Ids obtained before the reset must not be compared against ids obtained afterwards.
+#[cfg(feature = "testing")]
+#[cfg_attr(feature = "nightly-docs", doc(cfg(feature = "testing")))]
+pub unsafe fn reset_all_for_testing() {
+    // SAFETY: Guaranteed by the caller
+    unsafe {
+        unique::reset_for_testing();
+    }
+    #[cfg(feature = "std")]
+    // SAFETY: Guaranteed by the caller
+    unsafe {
+        live::reset_allocator();
+    }
+}
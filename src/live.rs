@@ -3,14 +3,24 @@
 //! The implementation is inspired by the implementation of thread ids in the [`thread_local`] crate:
 //! <https://github.com/Amanieu/thread_local-rs/blob/8958483/src/thread_id.rs>
 
-use alloc::collections::BinaryHeap;
-use core::cell::Cell;
-use core::fmt::{Debug, Formatter};
+use alloc::boxed::Box;
+use alloc::collections::{BTreeMap, BinaryHeap};
+use alloc::vec::Vec;
+use core::cell::{Cell, RefCell};
+use core::fmt::{Debug, Display, Formatter};
+use core::hash::{Hash, Hasher};
 
+use equivalent::Equivalent;
 use nonmax::NonMaxUsize;
 
-use crate::utils::OnceCell;
+pub use self::set::ThreadIdSet;
+use self::table::ThreadTable;
+use crate::AccessError;
 use crate::utils::sync::{Mutex, MutexGuard};
+use crate::utils::{ColdPath, ReentrancyGuard};
+
+mod set;
+mod table;
 
 /// Identifies a live thread.
 ///
@@ -23,13 +33,29 @@ use crate::utils::sync::{Mutex, MutexGuard};
 ///
 /// It is guaranteed that `Option<LiveThreadId>` has the same representation as `LiveThreadId`.
 /// Currently [`LiveThreadId::to_int`] can be zero, reducing wasted indexes.
-#[derive(Copy, Clone, Eq, PartialEq, Ord, PartialOrd, Hash)]
+#[derive(Copy, Clone, Eq, PartialEq, Ord, PartialOrd)]
 #[must_use]
 #[repr(transparent)]
 pub struct LiveThreadId {
     index: NonMaxUsize,
 }
 impl LiveThreadId {
+    /// A placeholder id, which is distinct from the id of any live thread.
+    ///
+    /// This reserves the largest index which `Option<LiveThreadId>` can represent (`usize::MAX - 1`),
+    /// which would otherwise only be reached if a program had that many threads alive at once.
+    /// It is returned by [`LiveThreadId::default`].
+    pub const SENTINEL: LiveThreadId = LiveThreadId {
+        index: NonMaxUsize::MAX,
+    };
+
+    /// Check if this id is the [`LiveThreadId::SENTINEL`] placeholder.
+    #[inline]
+    #[must_use]
+    pub fn is_sentinel(self) -> bool {
+        self == Self::SENTINEL
+    }
+
     /// Get the id of the currently executing thread.
     ///
     /// Ids will be reused once a thread dies.
@@ -46,47 +72,227 @@ impl LiveThreadId {
             }
         })
     }
+
+    /// Get the id of the currently executing thread if it has already been assigned one,
+    /// without allocating a new id.
+    ///
+    /// Returns `None` if the thread has never requested its id,
+    /// or if the id has already been released during thread teardown.
+    /// Since allocation requires acquiring a global lock,
+    /// this is useful in fast paths which must avoid contention entirely.
+    #[inline]
+    #[must_use]
+    pub fn current_or_none() -> Option<Self> {
+        LIVE_ID.try_with(Cell::get).ok().flatten()
+    }
+
+    /// Check if this is the id of the currently executing thread.
+    ///
+    /// Uses [`Self::current_or_none`], so checking from a thread
+    /// which has not been assigned an id returns `false` without allocating one.
+    #[inline]
+    #[must_use]
+    pub fn is_current(self) -> bool {
+        Self::current_or_none() == Some(self)
+    }
+
+    /// Look up the id of the currently executing thread once, then pass it to the specified closure.
+    ///
+    /// This is equivalent to calling [`Self::current`] at the start of the closure,
+    /// but makes it clear that repeated uses of the id do not require repeated lookups.
+    ///
+    /// May panic if called from a thread destructor.
+    #[inline]
+    pub fn with_current<R>(func: impl FnOnce(LiveThreadId) -> R) -> R {
+        func(Self::current())
+    }
+
+    /// Get the id of the currently executing thread,
+    /// returning an error instead of panicking if it cannot be accessed.
+    ///
+    /// ## Errors
+    /// Returns [`AccessError::Destroyed`] if called from a thread destructor
+    /// after the id has been released for reuse.
+    #[inline]
+    pub fn try_current() -> Result<Self, AccessError> {
+        match LIVE_ID.try_with(Cell::get) {
+            Ok(Some(existing)) => Ok(existing),
+            Ok(None) => {
+                let new_id = Self::try_alloc()?;
+                LIVE_ID.with(|cell| cell.set(Some(new_id)));
+                Ok(new_id)
+            }
+            Err(_) => Err(AccessError::Destroyed),
+        }
+    }
 }
 // SAFETY: Differs across live threads
 unsafe impl crate::IThreadId for LiveThreadId {
+    type Raw = NonMaxUsize;
+
     #[inline]
     fn current() -> Self {
         <Self>::current()
     }
+
+    #[inline]
+    fn to_raw(self) -> NonMaxUsize {
+        self.index
+    }
+
+    #[inline]
+    fn try_current() -> Result<Self, AccessError> {
+        <Self>::try_current()
+    }
+
+    #[inline]
+    fn to_nonzero_u128(self) -> Option<core::num::NonZeroU128> {
+        // the index is less than usize::MAX, so this can't overflow
+        core::num::NonZeroU128::new(self.index() as u128 + 1)
+    }
+
+    #[inline]
+    fn as_u64(&self) -> u64 {
+        self.to_int() as u64
+    }
+}
+impl Default for LiveThreadId {
+    /// Returns the [`LiveThreadId::SENTINEL`] placeholder.
+    #[inline]
+    fn default() -> Self {
+        Self::SENTINEL
+    }
+}
+/// Hashes the value of [`LiveThreadId::to_int`] using a single call to [`Hasher::write_usize`],
+/// so the id can be its own hash.
+impl Hash for LiveThreadId {
+    #[inline]
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        state.write_usize(self.to_int());
+    }
 }
 impl Debug for LiveThreadId {
     fn fmt(&self, f: &mut Formatter<'_>) -> core::fmt::Result {
         f.debug_tuple("LiveThreadId").field(&self.index()).finish()
     }
 }
+impl PartialEq<usize> for LiveThreadId {
+    #[inline]
+    fn eq(&self, other: &usize) -> bool {
+        self.to_int() == *other
+    }
+}
+impl PartialEq<LiveThreadId> for usize {
+    #[inline]
+    fn eq(&self, other: &LiveThreadId) -> bool {
+        *self == other.to_int()
+    }
+}
+/// Allows looking up an integer in a map keyed by [`LiveThreadId`].
+///
+/// This is consistent with [`Hash`], which hashes the same integer.
+impl Equivalent<LiveThreadId> for usize {
+    #[inline]
+    fn equivalent(&self, key: &LiveThreadId) -> bool {
+        *self == key.to_int()
+    }
+}
+/// Allows looking up a [`LiveThreadId`] in a map keyed by integers.
+impl Equivalent<usize> for LiveThreadId {
+    #[inline]
+    fn equivalent(&self, key: &usize) -> bool {
+        self.to_int() == *key
+    }
+}
+impl Display for LiveThreadId {
+    fn fmt(&self, f: &mut Formatter<'_>) -> core::fmt::Result {
+        write!(f, "{}", self.index())
+    }
+}
+/// Formats the value of [`LiveThreadId::to_int`], respecting all formatting flags.
+impl core::fmt::LowerHex for LiveThreadId {
+    fn fmt(&self, f: &mut Formatter<'_>) -> core::fmt::Result {
+        core::fmt::LowerHex::fmt(&self.to_int(), f)
+    }
+}
+/// Formats the value of [`LiveThreadId::to_int`], respecting all formatting flags.
+impl core::fmt::UpperHex for LiveThreadId {
+    fn fmt(&self, f: &mut Formatter<'_>) -> core::fmt::Result {
+        core::fmt::UpperHex::fmt(&self.to_int(), f)
+    }
+}
 impl LiveThreadId {
     #[cold]
     fn alloc() -> LiveThreadId {
+        Self::try_alloc().unwrap_or_else(|_| panic!("thread already destroyed"))
+    }
+
+    #[cold]
+    fn try_alloc() -> Result<LiveThreadId, AccessError> {
+        let _guard = ReentrancyGuard::enter(ColdPath::Live);
         GUARD
-            .try_with(|cell| {
-                assert!(cell.get().is_none(), "already initialized");
+            .try_with(|guard| {
+                assert!(guard.id.get().is_none(), "already initialized");
             })
-            .unwrap_or_else(|_| panic!("thread already destroyed"));
-        let mut alloc = ThreadIdAllocator::lock();
-        let alloc = ThreadIdAllocator::lazy_init(&mut alloc);
-        let new_id = if let Some(existing) = alloc.free_list.pop() {
-            LiveThreadId { index: existing.0 }
-        } else {
-            let next_id = alloc.next_id.get();
-            alloc.next_id.set(
-                next_id
-                    .get()
-                    .checked_add(1)
-                    .and_then(NonMaxUsize::new)
-                    .expect("LiveThreadId overflowed a usize"),
-            );
-            LiveThreadId { index: next_id }
+            .map_err(|_| AccessError::Destroyed)?;
+        let (new_id, generation) = {
+            let mut lock = ThreadIdAllocator::lock();
+            let allocator = ThreadIdAllocator::lazy_init(&mut lock);
+            let new_id = allocator.alloc();
+            (new_id, allocator.generation(new_id))
         };
-        GUARD.with(|cell| {
-            cell.set(ThreadGuard { id: new_id })
-                .unwrap_or_else(|_| panic!("already initialized"));
+        GUARD.with(|guard| {
+            guard.id.set(Some(new_id));
+            guard.generation.set(generation);
         });
-        new_id
+        Ok(new_id)
+    }
+
+    /// Count the number of ids which are currently in use by live threads.
+    ///
+    /// Threads which have never requested a [`LiveThreadId`] are not counted.
+    /// This is useful for sizing a vector indexed by [`LiveThreadId::to_int`].
+    ///
+    /// The value is a racy snapshot, and may be stale the moment it returns.
+    #[must_use]
+    pub fn live_count() -> usize {
+        ThreadIdAllocator::lock()
+            .as_ref()
+            .map_or(0, ThreadIdAllocator::live_count)
+    }
+
+    /// Get the largest index which has ever been allocated,
+    /// or `None` if no thread has ever requested a [`LiveThreadId`].
+    ///
+    /// This is the high-water mark of the allocator,
+    /// which is useful for preallocating a vector indexed by [`LiveThreadId::to_int`].
+    /// Every id which is currently in use has an index no greater than this value.
+    ///
+    /// The indices of dead threads are reused before any new index is allocated,
+    /// so this value never decreases and only grows when more threads are alive than ever before.
+    /// Freeing and reusing indices has no effect on the result.
+    #[must_use]
+    pub fn max_allocated() -> Option<usize> {
+        ThreadIdAllocator::lock()
+            .as_ref()
+            .and_then(ThreadIdAllocator::max_allocated)
+    }
+
+    /// Create a [`LiveThreadId`] from an integer value, returning `None` if it is `usize::MAX`.
+    ///
+    /// The value `usize::MAX` is reserved as the niche of `Option<LiveThreadId>`.
+    /// Rejecting it means passing an invalid integer is not undefined behavior.
+    /// This is still `unsafe`, because code relying on [`IThreadId`](crate::IThreadId)
+    /// may assume that no two live threads share an id,
+    /// so an id must not be forged from an arbitrary integer.
+    ///
+    /// ## Safety
+    /// If the integer is accepted, it must originate from calling [`LiveThreadId::to_int`]
+    /// on the id of a thread which is still alive.
+    #[inline]
+    #[must_use]
+    pub unsafe fn try_from_int(x: usize) -> Option<Self> {
+        NonMaxUsize::new(x).map(|index| LiveThreadId { index })
     }
 
     /// Get the integer value of this thread id.
@@ -95,7 +301,7 @@ impl LiveThreadId {
     #[inline]
     #[must_use]
     pub fn index(self) -> usize {
-        self.index.get()
+        self.to_int()
     }
 
     /// Get the integer value of this thread id.
@@ -108,7 +314,50 @@ impl LiveThreadId {
     #[inline]
     #[must_use]
     pub fn to_int(self) -> usize {
-        self.index.get()
+        let value = self.index.get();
+        debug_assert_ne!(value, usize::MAX, "LiveThreadId niche violated");
+        value
+    }
+
+    /// Pack an optional id into an integer, where `usize::MAX` represents `None`.
+    ///
+    /// This is stable across versions, unlike the in-memory representation of `Option<LiveThreadId>`,
+    /// so it can be stored in an atomic integer for lock-free algorithms.
+    /// Note that `usize::MAX` is distinct from [`LiveThreadId::SENTINEL`], which is a valid id.
+    /// Use [`LiveThreadId::from_int_opt`] to unpack the value.
+    #[inline]
+    #[must_use]
+    pub fn to_int_opt(id: Option<Self>) -> usize {
+        match id {
+            Some(id) => id.to_int(),
+            None => usize::MAX,
+        }
+    }
+
+    /// Unpack an optional id from an integer created by [`LiveThreadId::to_int_opt`],
+    /// where `usize::MAX` represents `None`.
+    ///
+    /// ## Safety
+    /// Like [`LiveThreadId::try_from_int`], the integer must either be `usize::MAX`,
+    /// or originate from the id of a thread which is still alive.
+    #[inline]
+    #[must_use]
+    pub unsafe fn from_int_opt(x: usize) -> Option<Self> {
+        // SAFETY: Guaranteed by the caller
+        unsafe { Self::try_from_int(x) }
+    }
+
+    /// Assert that the invariants of this id hold.
+    ///
+    /// The niche of the id is relied upon by `Option<LiveThreadId>` and the serde support,
+    /// so a violation indicates undefined behavior has already occurred.
+    /// This is intended as a test aid, and is checked even in release builds.
+    ///
+    /// ## Panics
+    /// If the invariants have been violated.
+    #[track_caller]
+    pub fn debug_check_invariants(self) {
+        assert_ne!(self.index.get(), usize::MAX, "LiveThreadId niche violated");
     }
 }
 simple_serde_serialize!(LiveThreadId, |this| this.to_int());
@@ -128,33 +377,764 @@ impl slog::Value for LiveThreadId {
     }
 }
 
+/// Check if exactly one thread currently has a [`LiveThreadId`].
+///
+/// Only threads which have requested a [`LiveThreadId`] are counted,
+/// so other threads may exist even if this returns `true`.
+///
+/// The result is a racy snapshot, and may be stale the moment it returns,
+/// because a new thread could request an id right afterwards.
+/// It must be combined with other guarantees before skipping synchronization.
+#[must_use]
+pub fn is_single_threaded() -> bool {
+    LiveThreadId::live_count() == 1
+}
+
+/// Predict the [`LiveThreadId`] which will be allocated to the next thread requesting an id.
+///
+/// This is useful for deterministically testing logic which handles reused ids.
+///
+/// The prediction is racy, because another thread could request or release an id
+/// before the intended thread requests its id.
+pub fn predict_next_id() -> LiveThreadId {
+    ThreadIdAllocator::lazy_init(&mut ThreadIdAllocator::lock()).peek()
+}
+
+/// Statistics about the ids allocated by [`LiveThreadId::current`], returned by [`stats`].
+///
+/// This is useful for diagnosing churn in thread pools,
+/// where most ids should be reused rather than freshly allocated.
+/// Allocations for keys by [`current_for_key`] are counted as well,
+/// but not allocations by a [`ThreadIdNamespace`](crate::namespace::ThreadIdNamespace).
+#[derive(Copy, Clone, Debug, Default, Eq, PartialEq, Hash)]
+#[non_exhaustive]
+pub struct AllocStats {
+    /// The number of ids which were allocated without reusing the id of a dead thread.
+    ///
+    /// This is equal to one more than the largest id which has ever been allocated.
+    pub fresh: u64,
+    /// The number of ids which were reused from a dead thread.
+    pub reused: u64,
+    /// The largest number of ids which have been in use at the same time.
+    pub peak_live: usize,
+}
+
+/// Get a snapshot of the statistics of the [`LiveThreadId`] allocator.
+///
+/// The statistics are process-global, and only ever increase.
+/// They are only reset by `reset_allocator`, which requires the `testing` feature.
+#[must_use]
+pub fn stats() -> AllocStats {
+    ThreadIdAllocator::lock()
+        .as_ref()
+        .map_or_else(AllocStats::default, |allocator| allocator.stats)
+}
+
+/// Iterate over a snapshot of all the [`LiveThreadId`]s which are currently in use, in ascending order.
+///
+/// This includes the ids associated with keys by [`current_for_key`],
+/// but not the ids of a [`ThreadIdNamespace`](crate::namespace::ThreadIdNamespace).
+///
+/// The snapshot is taken while holding the lock on the allocator,
+/// so it is internally consistent, but it is not updated during iteration.
+/// Threads may die or request new ids while iterating,
+/// so a yielded id could already belong to a different thread or no thread at all.
+pub fn iter_live() -> impl Iterator<Item = LiveThreadId> {
+    let lock = ThreadIdAllocator::lock();
+    let ids = lock.as_ref().map_or_else(Vec::new, ThreadIdAllocator::snapshot_live);
+    drop(lock);
+    ids.into_iter()
+}
+
+/// Register a callback to run when the current thread dies, before its [`LiveThreadId`] is released.
+///
+/// The callback receives the id of the thread, which remains valid until all callbacks have run,
+/// so it is safe to use for cleaning up resources indexed by the id.
+/// Callbacks run in the reverse order of their registration.
+///
+/// Callbacks run during the destruction of thread-locals,
+/// so other thread-locals may have already been destroyed,
+/// and callbacks should avoid anything which could panic during thread teardown.
+/// Calling [`LiveThreadId::current`] from a callback returns the id being released,
+/// and never allocates a new one.
+///
+/// Registering a callback from inside another callback is not allowed,
+/// because the thread-local holding the callbacks is already being destroyed.
+/// In that case, the callback is dropped without running and this returns `false`.
+/// Otherwise, this returns `true`.
+///
+/// Requests a [`LiveThreadId`] for the current thread if it does not already have one.
+/// May panic if called from a thread destructor.
+pub fn on_thread_exit(callback: impl FnOnce(LiveThreadId) + Send + 'static) -> bool {
+    if LiveThreadId::try_current().is_err() {
+        return false;
+    }
+    GUARD
+        .try_with(|guard| guard.exit_callbacks.borrow_mut().push(Box::new(callback)))
+        .is_ok()
+}
+
+/// Release the memory used to track the ids of dead threads.
+///
+/// The allocator keeps a list of the ids which are free to be reused,
+/// which grows to the peak number of dead ids.
+/// Once the list is mostly empty, excess capacity is released automatically as ids are reused,
+/// but a spike of threads which die and are never replaced could leave the capacity allocated.
+/// This releases all excess capacity immediately.
+///
+/// Capacity reserved by [`reserve`] is retained.
+pub fn compact() {
+    if let Some(allocator) = ThreadIdAllocator::lock().as_mut() {
+        allocator.free_list.shrink_to(allocator.reserved);
+    }
+}
+
+/// Preallocate the memory needed to track at least `additional` more ids,
+/// so that the allocator does not need to grow while they are in use.
+///
+/// This does not assign any ids, it only reserves internal capacity,
+/// which is retained even by [`compact`].
+/// Allocating a [`LiveThreadId`] never requires memory, but releasing one may,
+/// so reserving capacity avoids allocating memory during thread teardown.
+pub fn reserve(additional: usize) {
+    let mut lock = ThreadIdAllocator::lock();
+    let allocator = ThreadIdAllocator::lazy_init(&mut lock);
+    allocator.free_list.reserve(additional);
+    allocator.generations.reserve(additional);
+    allocator.reserved = core::cmp::max(allocator.reserved, allocator.free_list.capacity());
+}
+
+/// Get the capacity of the list tracking the ids of dead threads.
+///
+/// This is only intended for testing memory usage.
+#[cfg(feature = "testing")]
+#[cfg_attr(feature = "nightly-docs", doc(cfg(feature = "testing")))]
+#[must_use]
+pub fn free_list_capacity() -> usize {
+    ThreadIdAllocator::lock()
+        .as_ref()
+        .map_or(0, |allocator| allocator.free_list.capacity())
+}
+
+/// Get the [`LiveThreadId`] associated with an external key, allocating one if necessary.
+///
+/// This allows a framework to densely index its logical workers (like the slots of a thread pool)
+/// independently of the OS threads which happen to run them.
+/// The id remains associated with the key until it is released by [`retire_key`],
+/// regardless of which threads call this function.
+///
+/// Ids are allocated from the same allocator as [`LiveThreadId::current`],
+/// so the id of a key never conflicts with the id of a live thread.
+/// A key counts towards [`LiveThreadId::live_count`] until it is retired.
+///
+/// # Performance
+/// Unlike [`LiveThreadId::current`], which is a single thread-local read,
+/// this requires acquiring a global lock and searching a map of all active keys.
+/// Callers should cache the result instead of calling this in a hot loop.
+pub fn current_for_key(key: u64) -> LiveThreadId {
+    let mut keys = KEYS.lock();
+    *keys
+        .get_or_insert_with(BTreeMap::new)
+        .entry(key)
+        .or_insert_with(|| ThreadIdAllocator::lazy_init(&mut ThreadIdAllocator::lock()).alloc())
+}
+
+/// Release the [`LiveThreadId`] associated with an external key, so that it can be reused.
+///
+/// Returns `false` if the key has no associated id.
+/// A subsequent call to [`current_for_key`] with the same key may return a different id.
+pub fn retire_key(key: u64) -> bool {
+    let removed = KEYS.lock().as_mut().and_then(|keys| keys.remove(&key));
+    match removed {
+        Some(id) => {
+            ThreadIdAllocator::lazy_init(&mut ThreadIdAllocator::lock()).free(id);
+            true
+        }
+        None => false,
+    }
+}
+
+/// The ids associated with external keys by [`current_for_key`].
+///
+/// Must be locked before the [`ALLOCATOR`].
+static KEYS: Mutex<Option<BTreeMap<u64, LiveThreadId>>> = Mutex::new(None);
+
+/// Get the index of the current thread among the workers of its rayon pool,
+/// or `None` if it is not a rayon worker.
+///
+/// This is equivalent to [`rayon::current_thread_index`].
+/// Worker indices are dense within a single pool, ranging from zero to [`rayon::current_num_threads`],
+/// so per-worker accumulators can be stored in a [`Vec`] sized to the pool.
+/// By contrast, a [`LiveThreadId`] is dense across all live threads of the process,
+/// including threads outside the pool and workers of other pools.
+///
+/// Prefer the worker index for state which is specific to one pool,
+/// and fall back to a [`ThreadIndexedVec`] keyed by [`LiveThreadId`] for code which may also run outside the pool:
+///
+/// ```
+/// use threadid::live::{self, ThreadIndexedVec};
+/// use std::sync::atomic::{AtomicUsize, Ordering};
+///
+/// let per_worker = (0..rayon::current_num_threads()).map(|_| AtomicUsize::new(0)).collect::<Vec<_>>();
+/// let elsewhere = ThreadIndexedVec::<AtomicUsize>::new();
+/// let counter = match live::rayon_worker_index() {
+///     Some(index) => &per_worker[index],
+///     None => elsewhere.get_or_insert_with(|| AtomicUsize::new(0)),
+/// };
+/// counter.fetch_add(1, Ordering::Relaxed);
+/// ```
+#[cfg(feature = "rayon")]
+#[cfg_attr(feature = "nightly-docs", doc(cfg(feature = "rayon")))]
+#[inline]
+#[must_use]
+pub fn rayon_worker_index() -> Option<usize> {
+    rayon::current_thread_index()
+}
+
+/// Partitions items into buckets by the [`LiveThreadId`] of the thread which pushed them.
+///
+/// This is useful for map-side aggregation in a parallel pipeline.
+/// Each thread only writes to its own bucket, so [`ThreadPartitioner::push`] is lock-free
+/// and never contends with other threads.
+/// The buckets are densely indexed by [`LiveThreadId::to_int`].
+/// Collecting the buckets with [`ThreadPartitioner::into_buckets`] requires exclusive access.
+///
+/// If a thread dies and its id is reused by a new thread,
+/// the new thread will append to the same bucket.
+pub struct ThreadPartitioner<T> {
+    buckets: ThreadTable<RefCell<Vec<T>>>,
+}
+impl<T> ThreadPartitioner<T> {
+    /// Create a new partitioner, with no buckets.
+    #[must_use]
+    pub fn new() -> Self {
+        ThreadPartitioner {
+            buckets: ThreadTable::new(),
+        }
+    }
+
+    /// Append an item to the bucket of the current thread.
+    #[inline]
+    pub fn push(&self, item: T) {
+        self.buckets
+            .get_or_insert_with(|| RefCell::new(Vec::new()))
+            .borrow_mut()
+            .push(item);
+    }
+
+    /// Collect the buckets of all threads which pushed items, ordered by their id.
+    #[must_use]
+    pub fn into_buckets(mut self) -> Vec<(LiveThreadId, Vec<T>)> {
+        self.buckets
+            .take_all()
+            .into_iter()
+            .map(|(id, bucket)| (id, bucket.into_inner()))
+            .collect()
+    }
+}
+impl<T> Default for ThreadPartitioner<T> {
+    #[inline]
+    fn default() -> Self {
+        ThreadPartitioner::new()
+    }
+}
+impl<T> Debug for ThreadPartitioner<T> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> core::fmt::Result {
+        f.debug_struct("ThreadPartitioner").finish_non_exhaustive()
+    }
+}
+
+/// A [`LiveThreadId`] paired with a generation, which detects when the id has been reused.
+///
+/// The allocator counts the number of times each id has been released,
+/// so a stored id can later be checked with [`VersionedLiveThreadId::is_stale`]
+/// to determine if the thread it refers to has died.
+///
+/// Requesting the current id is slightly slower than [`LiveThreadId::current`],
+/// because the generation is stored in a separate thread-local.
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Hash)]
+#[must_use]
+pub struct VersionedLiveThreadId {
+    id: LiveThreadId,
+    generation: u64,
+}
+impl VersionedLiveThreadId {
+    /// Get the versioned id of the currently executing thread.
+    ///
+    /// May panic if called from a thread destructor.
+    #[inline]
+    pub fn current() -> Self {
+        let id = LiveThreadId::current();
+        VersionedLiveThreadId {
+            id,
+            generation: GUARD.with(|guard| guard.generation.get()),
+        }
+    }
+
+    /// Get the underlying [`LiveThreadId`], ignoring the generation.
+    #[inline]
+    pub fn id(self) -> LiveThreadId {
+        self.id
+    }
+
+    /// Get the integer value of the underlying id.
+    #[inline]
+    #[must_use]
+    pub fn index(self) -> usize {
+        self.id.index()
+    }
+
+    /// Get the number of times the id had been released by dead threads before being allocated to this thread.
+    #[inline]
+    #[must_use]
+    pub fn generation(self) -> u64 {
+        self.generation
+    }
+
+    /// Check if the thread this id refers to has died,
+    /// meaning the id has been released and possibly reused by another thread.
+    ///
+    /// This requires acquiring the lock on the allocator.
+    /// The result is a racy snapshot, because the thread could die right afterwards.
+    #[must_use]
+    pub fn is_stale(self) -> bool {
+        ThreadIdAllocator::lock()
+            .as_ref()
+            .map_or(true, |allocator| allocator.generation(self.id) != self.generation)
+    }
+}
+
+/// A [`LiveThreadId`] and a truncated generation packed into a single `usize`,
+/// which detects when a slot indexed by the id has been recycled.
+///
+/// This is intended for lock-free structures which need to store the owner of a slot in a single atomic word.
+/// The low [`TaggedIndex::INDEX_BITS`] bits hold the [`LiveThreadId::index`],
+/// and the high [`TaggedIndex::EPOCH_BITS`] bits hold the [generation](VersionedLiveThreadId::generation)
+/// modulo <code>2<sup>EPOCH_BITS</sup></code>.
+/// On 64-bit platforms this is a 48-bit index and a 16-bit epoch,
+/// and on 32-bit platforms a 24-bit index and an 8-bit epoch.
+///
+/// ## Overflow
+/// The epoch wraps around once an index has been recycled <code>2<sup>EPOCH_BITS</sup></code> times,
+/// so a token which is held across that many deaths of threads sharing its index will match again.
+/// Use [`VersionedLiveThreadId`] if the full 64-bit generation is required.
+///
+/// An index which does not fit in [`TaggedIndex::INDEX_BITS`] cannot be tagged.
+/// This would require more simultaneously live threads than can exist in practice.
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Hash)]
+#[repr(transparent)]
+#[must_use]
+pub struct TaggedIndex(usize);
+impl TaggedIndex {
+    /// The number of low bits holding the index of the id.
+    pub const INDEX_BITS: u32 = usize::BITS / 4 * 3;
+    /// The number of high bits holding the epoch, which is the generation of the id modulo <code>2<sup>EPOCH_BITS</sup></code>.
+    pub const EPOCH_BITS: u32 = usize::BITS - Self::INDEX_BITS;
+    const INDEX_MASK: usize = (1 << Self::INDEX_BITS) - 1;
+
+    /// Tag a versioned id with its epoch,
+    /// returning `None` if its index does not fit in [`Self::INDEX_BITS`].
+    #[inline]
+    #[must_use]
+    pub fn from_versioned(id: VersionedLiveThreadId) -> Option<Self> {
+        let index = id.index();
+        if index > Self::INDEX_MASK {
+            return None;
+        }
+        #[allow(clippy::cast_possible_truncation)] // truncation is intended
+        let epoch = id.generation() as usize;
+        Some(TaggedIndex(index | (epoch << Self::INDEX_BITS)))
+    }
+
+    /// Check if this is the tag of the current thread.
+    ///
+    /// Returns `false` if the id has since been reused by another thread,
+    /// unless the epoch has wrapped around.
+    /// Unlike [`VersionedLiveThreadId::is_stale`], this only reads thread-locals and never acquires a lock.
+    ///
+    /// May panic if called from a thread destructor.
+    #[inline]
+    #[must_use]
+    pub fn matches_current(self) -> bool {
+        TaggedIndex::from_versioned(VersionedLiveThreadId::current()) == Some(self)
+    }
+
+    /// Get the underlying [`LiveThreadId`], ignoring the epoch.
+    #[inline]
+    pub fn id(self) -> LiveThreadId {
+        Self::id_at(self.index())
+    }
+
+    #[inline]
+    fn id_at(index: usize) -> LiveThreadId {
+        LiveThreadId {
+            // masking ensures the index is less than usize::MAX
+            index: NonMaxUsize::new(index).unwrap(),
+        }
+    }
+
+    /// Get the integer value of the underlying id.
+    #[inline]
+    #[must_use]
+    pub fn index(self) -> usize {
+        self.0 & Self::INDEX_MASK
+    }
+
+    /// Get the generation of the id, truncated to [`Self::EPOCH_BITS`].
+    #[inline]
+    #[must_use]
+    pub fn epoch(self) -> usize {
+        self.0 >> Self::INDEX_BITS
+    }
+
+    /// Get the packed representation of the tag, for storing in an atomic.
+    #[inline]
+    #[must_use]
+    pub fn to_bits(self) -> usize {
+        self.0
+    }
+
+    /// Reconstruct a tag from its packed representation.
+    ///
+    /// ## Safety
+    /// The value must have been returned by [`Self::to_bits`].
+    #[inline]
+    pub unsafe fn from_bits(bits: usize) -> Self {
+        TaggedIndex(bits)
+    }
+}
+
+/// Get the [`TaggedIndex`] of the currently executing thread.
+///
+/// May panic if called from a thread destructor.
+///
+/// ## Panics
+/// If the index of the current thread does not fit in [`TaggedIndex::INDEX_BITS`].
+pub fn current_tagged() -> TaggedIndex {
+    TaggedIndex::from_versioned(VersionedLiveThreadId::current()).expect("LiveThreadId overflowed a TaggedIndex")
+}
+
+/// Identifies a task running on an async executor, combining a [`LiveThreadId`] with a task id.
+///
+/// Executors share OS threads between many tasks, so thread ids alone cannot distinguish tasks.
+/// The task id must be provided by the executor, and is only required to be unique among the tasks
+/// running on the same thread at the same time.
+/// Since a [`LiveThreadId`] is reused once its thread dies,
+/// an [`ExecutionId`] is also only unique while its thread is alive.
+///
+/// Ids are ordered by thread, then by task.
+#[cfg(feature = "execution-id")]
+#[cfg_attr(feature = "nightly-docs", doc(cfg(feature = "execution-id")))]
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Hash, Ord, PartialOrd)]
+#[must_use]
+pub struct ExecutionId {
+    thread: LiveThreadId,
+    task: u64,
+}
+#[cfg(feature = "execution-id")]
+impl ExecutionId {
+    /// Combine the specified thread and task ids.
+    #[inline]
+    pub fn new(thread: LiveThreadId, task: u64) -> Self {
+        ExecutionId { thread, task }
+    }
+
+    /// Combine the id of the currently executing thread with the specified task id.
+    ///
+    /// May panic if called from a thread destructor.
+    #[inline]
+    pub fn current(task: u64) -> Self {
+        ExecutionId::new(LiveThreadId::current(), task)
+    }
+
+    /// Get the id of the thread which is executing the task.
+    #[inline]
+    pub fn thread(self) -> LiveThreadId {
+        self.thread
+    }
+
+    /// Get the id of the task, as provided by the executor.
+    #[inline]
+    #[must_use]
+    pub fn task(self) -> u64 {
+        self.task
+    }
+}
+#[cfg(feature = "execution-id")]
+impl Display for ExecutionId {
+    fn fmt(&self, f: &mut Formatter<'_>) -> core::fmt::Result {
+        write!(f, "{}.{}", self.thread, self.task)
+    }
+}
+#[cfg(all(feature = "execution-id", feature = "serde"))]
+#[cfg_attr(feature = "nightly-docs", doc(cfg(feature = "serde")))]
+impl serde::Serialize for ExecutionId {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        use serde::ser::SerializeStruct;
+        let mut ser = serializer.serialize_struct("ExecutionId", 2)?;
+        ser.serialize_field("thread", &self.thread)?;
+        ser.serialize_field("task", &self.task)?;
+        ser.end()
+    }
+}
+
+/// A growable vector with a slot for each [`LiveThreadId`].
+///
+/// Each thread lazily initializes its own slot with [`ThreadIndexedVec::get_or_insert_with`],
+/// which is lock-free and never contends with other threads.
+/// The slots are densely indexed by [`LiveThreadId::to_int`],
+/// and storage grows in geometrically sized chunks which are never moved,
+/// so references to a slot remain valid while the vector is borrowed.
+///
+/// If a thread dies and its id is reused by a new thread,
+/// the new thread inherits the value left in the slot by the dead thread.
+/// Values are only dropped when the vector itself is dropped.
+pub struct ThreadIndexedVec<T> {
+    slots: ThreadTable<T>,
+}
+impl<T> ThreadIndexedVec<T> {
+    /// Create a new vector, with no slots initialized.
+    #[must_use]
+    pub fn new() -> Self {
+        ThreadIndexedVec {
+            slots: ThreadTable::new(),
+        }
+    }
+
+    /// Get the slot of the current thread, initializing it if needed.
+    ///
+    /// If the slot was initialized by a dead thread with the same [`LiveThreadId`],
+    /// that value is returned without calling the initialization function.
+    ///
+    /// ## Panics
+    /// If called recursively from the initialization function.
+    #[inline]
+    pub fn get_or_insert_with(&self, init: impl FnOnce() -> T) -> &T {
+        self.slots.get_or_insert_with(init)
+    }
+
+    /// Iterate over the slots which have been initialized, along with their ids, ordered by id.
+    ///
+    /// Slots initialized by other threads during iteration may or may not be included.
+    /// The id of a slot may belong to a thread which has since died.
+    pub fn iter(&self) -> impl Iterator<Item = (LiveThreadId, &T)> + '_
+    where
+        T: Sync,
+    {
+        self.slots.iter()
+    }
+
+    /// Mutably iterate over the slots which have been initialized, along with their ids, ordered by id.
+    pub fn iter_mut(&mut self) -> impl Iterator<Item = (LiveThreadId, &mut T)> + '_ {
+        self.slots.iter_mut()
+    }
+}
+impl<T> Default for ThreadIndexedVec<T> {
+    #[inline]
+    fn default() -> Self {
+        ThreadIndexedVec::new()
+    }
+}
+impl<T> Debug for ThreadIndexedVec<T> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> core::fmt::Result {
+        f.debug_struct("ThreadIndexedVec").finish_non_exhaustive()
+    }
+}
+
 fast_thread_local! {
     static LIVE_ID: Cell<Option<LiveThreadId>> = Cell::new(None);
 }
 std::thread_local! {
     /// Runs a destructor to reuse a thread id
-    static GUARD: OnceCell<ThreadGuard> = const { OnceCell::new() };
+    static GUARD: ThreadGuard = const {
+        ThreadGuard {
+            id: Cell::new(None),
+            generation: Cell::new(0),
+            exit_callbacks: RefCell::new(Vec::new()),
+        }
+    };
 }
+/// The size of the thread-locals declared by this module.
+pub(crate) const LOCALS_SIZE: usize =
+    core::mem::size_of::<Cell<Option<LiveThreadId>>>() + core::mem::size_of::<ThreadGuard>();
 struct ThreadGuard {
-    id: LiveThreadId,
+    id: Cell<Option<LiveThreadId>>,
+    /// The generation of the id, used by [`VersionedLiveThreadId`].
+    generation: Cell<u64>,
+    /// The callbacks registered by [`on_thread_exit`].
+    exit_callbacks: RefCell<Vec<ExitCallback>>,
 }
+type ExitCallback = Box<dyn FnOnce(LiveThreadId) + Send>;
 impl Drop for ThreadGuard {
     fn drop(&mut self) {
-        let _ = LIVE_ID.try_with(|id| id.set(None));
-        let mut alloc = ThreadIdAllocator::lock();
-        let alloc = ThreadIdAllocator::lazy_init(&mut alloc);
-        alloc.free_list.push(core::cmp::Reverse(self.id.index));
+        if let Some(id) = self.id.get() {
+            while let Some(callback) = self.exit_callbacks.get_mut().pop() {
+                callback(id);
+            }
+            let _guard = ReentrancyGuard::enter(ColdPath::Live);
+            let _ = LIVE_ID.try_with(|id| id.set(None));
+            ThreadIdAllocator::lazy_init(&mut ThreadIdAllocator::lock()).free(id);
+        }
     }
 }
 
+/// Reset the allocator used by [`LiveThreadId`] and clear the id of the current thread,
+/// as if no ids had ever been allocated.
+///
+/// The next id to be allocated will be zero, and the free list is emptied.
+/// Ids associated with keys by [`current_for_key`] are also discarded.
+/// This is intended for single-threaded test setup, to make assertions about specific ids deterministic.
+/// Unlike [`crate::reset_all_for_testing`], the ids of other types are not affected.
+///
+/// The current thread releases its id as if it had died,
+/// so the callbacks it registered with [`on_thread_exit`] run before the reset.
+///
+/// ## Safety
+/// No other threads which hold a [`LiveThreadId`] may be running.
+/// Otherwise, their ids would be handed out again, so two live threads could share the same id.
+///
+/// Ids obtained before the reset must not be compared against ids obtained afterwards.
+#[cfg(feature = "testing")]
+#[cfg_attr(feature = "nightly-docs", doc(cfg(feature = "testing")))]
+pub unsafe fn reset_allocator() {
+    let _ = GUARD.try_with(|guard| {
+        if let Some(id) = guard.id.take() {
+            loop {
+                // release the borrow before running the callback, which may register another
+                let callback = guard.exit_callbacks.borrow_mut().pop();
+                match callback {
+                    Some(callback) => callback(id),
+                    None => break,
+                }
+            }
+        }
+    });
+    let _ = LIVE_ID.try_with(|id| id.set(None));
+    *KEYS.lock() = None;
+    *ThreadIdAllocator::lock() = None;
+}
+
+/// The capacity of the free list which is never released by [`ThreadIdAllocator::maybe_shrink`],
+/// in addition to any capacity reserved by [`reserve`].
+const MIN_FREE_LIST_CAPACITY: usize = 64;
+
 /// Reuses the thread ids of dead threads.
 static ALLOCATOR: Mutex<Option<ThreadIdAllocator>> = Mutex::new(None);
 
-struct ThreadIdAllocator {
+pub(crate) struct ThreadIdAllocator {
     next_id: Cell<NonMaxUsize>,
     free_list: BinaryHeap<core::cmp::Reverse<NonMaxUsize>>,
+    /// The number of times each index has been freed.
+    ///
+    /// Indices which have never been freed may be missing.
+    generations: Vec<u64>,
+    /// The capacity of the free list reserved by [`reserve`], which is never released.
+    reserved: usize,
+    stats: AllocStats,
 }
 impl ThreadIdAllocator {
+    pub(crate) fn new() -> ThreadIdAllocator {
+        ThreadIdAllocator {
+            free_list: BinaryHeap::new(),
+            next_id: Cell::new(NonMaxUsize::ZERO),
+            generations: Vec::new(),
+            reserved: 0,
+            stats: AllocStats {
+                fresh: 0,
+                reused: 0,
+                peak_live: 0,
+            },
+        }
+    }
+
+    /// Allocate the smallest id which is not currently in use.
+    pub(crate) fn alloc(&mut self) -> LiveThreadId {
+        let id = if let Some(existing) = self.free_list.pop() {
+            self.maybe_shrink();
+            self.stats.reused += 1;
+            LiveThreadId { index: existing.0 }
+        } else {
+            let next_id = self.next_id.get();
+            // the largest index is reserved for the sentinel
+            assert!(
+                next_id < LiveThreadId::SENTINEL.index,
+                "LiveThreadId overflowed a usize"
+            );
+            self.next_id.set(NonMaxUsize::new(next_id.get() + 1).unwrap());
+            self.stats.fresh += 1;
+            LiveThreadId { index: next_id }
+        };
+        self.stats.peak_live = core::cmp::max(self.stats.peak_live, self.live_count());
+        id
+    }
+
+    /// Release excess capacity of the free list once it is mostly unused.
+    ///
+    /// Only shrinks once the capacity is four times the length,
+    /// and then only halves the excess, so the cost is amortized across the pops which emptied it.
+    #[inline]
+    fn maybe_shrink(&mut self) {
+        let capacity = self.free_list.capacity();
+        let min_capacity = core::cmp::max(MIN_FREE_LIST_CAPACITY, self.reserved);
+        if capacity > min_capacity && capacity / 4 > self.free_list.len() {
+            self.free_list
+                .shrink_to(core::cmp::max(self.free_list.len() * 2, min_capacity));
+        }
+    }
+
+    /// Get the id which will be returned by the next call to [`Self::alloc`].
+    pub(crate) fn peek(&self) -> LiveThreadId {
+        match self.free_list.peek() {
+            Some(existing) => LiveThreadId { index: existing.0 },
+            None => LiveThreadId {
+                index: self.next_id.get(),
+            },
+        }
+    }
+
+    /// Count the number of ids which are currently in use.
+    pub(crate) fn live_count(&self) -> usize {
+        self.next_id.get().get() - self.free_list.len()
+    }
+
+    /// Get the largest index which has ever been allocated.
+    pub(crate) fn max_allocated(&self) -> Option<usize> {
+        self.next_id.get().get().checked_sub(1)
+    }
+
+    /// Collect all of the ids which are currently in use, in ascending order.
+    fn snapshot_live(&self) -> Vec<LiveThreadId> {
+        let mut free = self.free_list.iter().map(|id| id.0.get()).collect::<Vec<_>>();
+        free.sort_unstable();
+        let mut free = free.into_iter().peekable();
+        let mut live = Vec::with_capacity(self.live_count());
+        for index in 0..self.next_id.get().get() {
+            if free.peek() == Some(&index) {
+                free.next();
+            } else {
+                live.push(LiveThreadId {
+                    index: NonMaxUsize::new(index).unwrap(),
+                });
+            }
+        }
+        live
+    }
+
+    /// Get the number of times an id has been freed.
+    pub(crate) fn generation(&self, id: LiveThreadId) -> u64 {
+        self.generations.get(id.index()).copied().unwrap_or(0)
+    }
+
+    /// Mark an id as free, so that it can be reused.
+    pub(crate) fn free(&mut self, id: LiveThreadId) {
+        let index = id.index();
+        if index >= self.generations.len() {
+            self.generations.resize(index + 1, 0);
+        }
+        self.generations[index] += 1;
+        self.free_list.push(core::cmp::Reverse(id.index));
+    }
+
     #[inline]
     fn lock() -> MutexGuard<'static, Option<ThreadIdAllocator>> {
         ALLOCATOR.lock()
@@ -163,10 +1143,7 @@ impl ThreadIdAllocator {
     fn lazy_init<'a>(lock: &'a mut MutexGuard<'static, Option<ThreadIdAllocator>>) -> &'a mut ThreadIdAllocator {
         #[cold]
         fn init() -> ThreadIdAllocator {
-            ThreadIdAllocator {
-                free_list: BinaryHeap::new(),
-                next_id: Cell::new(NonMaxUsize::ZERO),
-            }
+            ThreadIdAllocator::new()
         }
         lock.get_or_insert_with(init)
     }
@@ -0,0 +1,145 @@
+//! Defines [`ThreadIdSet`].
+
+use alloc::vec::Vec;
+use core::fmt::{Debug, Formatter};
+
+use nonmax::NonMaxUsize;
+
+use super::LiveThreadId;
+
+/// The number of bits in each word of a [`ThreadIdSet`].
+const WORD_BITS: usize = u64::BITS as usize;
+
+/// A set of [`LiveThreadId`]s, stored as a bitset indexed by [`LiveThreadId::to_int`].
+///
+/// Since live ids are dense, this uses a single bit for each thread,
+/// and membership is checked without hashing.
+/// The bitset grows as needed to fit the largest id which has been inserted,
+/// and never shrinks unless cleared.
+///
+/// If a thread dies and its id is reused by a new thread,
+/// the new thread will be considered a member of the set.
+/// To detect this, store [`VersionedLiveThreadId`](super::VersionedLiveThreadId)s in a different collection.
+///
+/// The set has a single owner, requiring `&mut self` to insert or remove ids.
+/// Wrap it in a lock to share it between threads.
+#[derive(Clone, Default)]
+pub struct ThreadIdSet {
+    words: Vec<u64>,
+}
+impl ThreadIdSet {
+    /// Create a new empty set, without allocating.
+    #[inline]
+    #[must_use]
+    pub const fn new() -> Self {
+        ThreadIdSet { words: Vec::new() }
+    }
+
+    /// Insert the specified id into the set, returning `true` if it was not already present.
+    ///
+    /// The [`LiveThreadId::SENTINEL`] placeholder is never a member of the set,
+    /// so inserting it does nothing and returns `false`.
+    /// Otherwise its index would require growing the bitset to cover nearly all of memory.
+    pub fn insert(&mut self, id: LiveThreadId) -> bool {
+        if id.is_sentinel() {
+            return false;
+        }
+        let (word, mask) = Self::locate(id);
+        if word >= self.words.len() {
+            self.words.resize(word + 1, 0);
+        }
+        let present = self.words[word] & mask != 0;
+        self.words[word] |= mask;
+        !present
+    }
+
+    /// Insert the id of the current thread into the set, returning `true` if it was not already present.
+    ///
+    /// May panic if called from a thread destructor.
+    #[inline]
+    pub fn insert_current(&mut self) -> bool {
+        self.insert(LiveThreadId::current())
+    }
+
+    /// Remove the specified id from the set, returning `true` if it was present.
+    pub fn remove(&mut self, id: LiveThreadId) -> bool {
+        let (word, mask) = Self::locate(id);
+        match self.words.get_mut(word) {
+            Some(bits) => {
+                let present = *bits & mask != 0;
+                *bits &= !mask;
+                present
+            }
+            None => false,
+        }
+    }
+
+    /// Check if the specified id is a member of the set.
+    #[inline]
+    #[must_use]
+    pub fn contains(&self, id: LiveThreadId) -> bool {
+        let (word, mask) = Self::locate(id);
+        self.words.get(word).map_or(false, |&bits| bits & mask != 0)
+    }
+
+    /// Count the number of ids in the set.
+    ///
+    /// This requires scanning the entire bitset.
+    #[must_use]
+    pub fn len(&self) -> usize {
+        self.words.iter().map(|bits| bits.count_ones() as usize).sum()
+    }
+
+    /// Check if the set contains no ids.
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.words.iter().all(|&bits| bits == 0)
+    }
+
+    /// Remove all ids from the set, releasing its memory.
+    pub fn clear(&mut self) {
+        self.words = Vec::new();
+    }
+
+    /// Iterate over the ids in the set, ordered by id.
+    pub fn iter(&self) -> impl Iterator<Item = LiveThreadId> + '_ {
+        self.words.iter().enumerate().flat_map(|(word, &bits)| {
+            (0..WORD_BITS)
+                .filter(move |bit| bits & (1 << bit) != 0)
+                .map(move |bit| Self::id_at(word * WORD_BITS + bit))
+        })
+    }
+
+    #[inline]
+    fn id_at(index: usize) -> LiveThreadId {
+        LiveThreadId {
+            // only the bits of valid ids are ever set
+            index: NonMaxUsize::new(index).unwrap(),
+        }
+    }
+
+    #[inline]
+    fn locate(id: LiveThreadId) -> (usize, u64) {
+        let index = id.to_int();
+        (index / WORD_BITS, 1 << (index % WORD_BITS))
+    }
+}
+impl FromIterator<LiveThreadId> for ThreadIdSet {
+    fn from_iter<I: IntoIterator<Item = LiveThreadId>>(iter: I) -> Self {
+        let mut set = ThreadIdSet::new();
+        set.extend(iter);
+        set
+    }
+}
+impl Extend<LiveThreadId> for ThreadIdSet {
+    fn extend<I: IntoIterator<Item = LiveThreadId>>(&mut self, iter: I) {
+        for id in iter {
+            self.insert(id);
+        }
+    }
+}
+impl Debug for ThreadIdSet {
+    fn fmt(&self, f: &mut Formatter<'_>) -> core::fmt::Result {
+        f.debug_set().entries(self.iter()).finish()
+    }
+}
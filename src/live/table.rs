@@ -0,0 +1,239 @@
+//! Defines [`ThreadTable`], a lock-free table with an entry for each [`LiveThreadId`].
+//!
+//! The implementation is based on the `ThreadLocal` type from the [`thread_local`] crate:
+//! <https://github.com/Amanieu/thread_local-rs/blob/8958483/src/lib.rs>
+
+use alloc::boxed::Box;
+use alloc::vec::Vec;
+use core::cell::UnsafeCell;
+use core::mem::MaybeUninit;
+use core::ptr;
+use core::sync::atomic::{AtomicBool, AtomicPtr, Ordering};
+
+use nonmax::NonMaxUsize;
+
+use super::LiveThreadId;
+
+/// The number of buckets, each of which is twice the size of the previous one.
+const BUCKETS: usize = usize::BITS as usize;
+
+/// A table with an entry for each [`LiveThreadId`].
+///
+/// Only the thread which currently owns an id may initialize or access its entry,
+/// so no synchronization is required besides lazily allocating the buckets.
+/// If a thread dies and its id is reused, the new thread inherits the existing entry.
+pub(crate) struct ThreadTable<T> {
+    buckets: [AtomicPtr<Entry<T>>; BUCKETS],
+}
+// SAFETY: Values are only accessed by the thread which currently owns their id,
+// so they only need to be sent between threads
+unsafe impl<T: Send> Send for ThreadTable<T> {}
+// SAFETY: Shared access is only given to the thread which currently owns an id
+unsafe impl<T: Send> Sync for ThreadTable<T> {}
+impl<T> ThreadTable<T> {
+    pub(crate) fn new() -> ThreadTable<T> {
+        ThreadTable {
+            buckets: core::array::from_fn(|_| AtomicPtr::new(ptr::null_mut())),
+        }
+    }
+
+    /// Get the entry of the current thread, initializing it if needed.
+    ///
+    /// Panics if called recursively from the initialization function.
+    #[inline]
+    pub(crate) fn get_or_insert_with(&self, init: impl FnOnce() -> T) -> &T {
+        let entry = self.entry(Location::of(LiveThreadId::current()));
+        if !entry.present.load(Ordering::Acquire) {
+            Self::insert(entry, init());
+        }
+        // SAFETY: The value will not be modified until we have exclusive access
+        let value = unsafe { &*entry.value.get() };
+        // SAFETY: The entry is present
+        unsafe { value.assume_init_ref() }
+    }
+
+    #[cold]
+    fn insert(entry: &Entry<T>, value: T) {
+        assert!(!entry.present.load(Ordering::Acquire), "entry initialized recursively");
+        // SAFETY: The current thread owns the entry, and it is not yet initialized,
+        // so there are no other references to the value.
+        unsafe {
+            (*entry.value.get()).write(value);
+        }
+        entry.present.store(true, Ordering::Release);
+    }
+
+    /// Get the entry at the specified location, allocating its bucket if needed.
+    #[inline]
+    fn entry(&self, location: Location) -> &Entry<T> {
+        let bucket = &self.buckets[location.bucket];
+        let mut entries = bucket.load(Ordering::Acquire);
+        if entries.is_null() {
+            entries = Self::alloc_bucket(bucket, location.bucket_size);
+        }
+        // SAFETY: The offset is within the size of the bucket
+        let entry = unsafe { entries.add(location.offset) };
+        // SAFETY: The bucket is allocated, and is never freed while we are borrowed
+        unsafe { &*entry }
+    }
+
+    #[cold]
+    fn alloc_bucket(bucket: &AtomicPtr<Entry<T>>, size: usize) -> *mut Entry<T> {
+        let new_entries =
+            Box::into_raw((0..size).map(|_| Entry::new()).collect::<Box<[Entry<T>]>>()).cast::<Entry<T>>();
+        match bucket.compare_exchange(ptr::null_mut(), new_entries, Ordering::AcqRel, Ordering::Acquire) {
+            Ok(_) => new_entries,
+            Err(existing) => {
+                // SAFETY: Another thread allocated the bucket first, so ours was never shared
+                drop(unsafe { Box::from_raw(ptr::slice_from_raw_parts_mut(new_entries, size)) });
+                existing
+            }
+        }
+    }
+
+    /// Iterate over the entries which are present, along with their ids.
+    ///
+    /// The entries are ordered by their id.
+    /// Entries initialized concurrently with iteration may or may not be included.
+    ///
+    /// The caller must ensure `T: Sync` before sharing the references with other threads.
+    pub(crate) fn iter(&self) -> impl Iterator<Item = (LiveThreadId, &T)> + '_ {
+        self.buckets.iter().enumerate().flat_map(|(bucket_index, bucket)| {
+            let entries = bucket.load(Ordering::Acquire);
+            let entries: &[Entry<T>] = if entries.is_null() {
+                &[]
+            } else {
+                // SAFETY: The bucket is allocated with the specified size, and is never freed while we are borrowed
+                unsafe { &*ptr::slice_from_raw_parts(entries, 1usize << bucket_index) }
+            };
+            entries.iter().enumerate().filter_map(move |(offset, entry)| {
+                if entry.present.load(Ordering::Acquire) {
+                    // SAFETY: Present values are never modified until we have exclusive access
+                    let value = unsafe { &*entry.value.get() };
+                    // SAFETY: The entry is present
+                    Some((Location::id_at(bucket_index, offset), unsafe {
+                        value.assume_init_ref()
+                    }))
+                } else {
+                    None
+                }
+            })
+        })
+    }
+
+    /// Mutably iterate over the entries which are present, along with their ids.
+    ///
+    /// The entries are ordered by their id.
+    pub(crate) fn iter_mut(&mut self) -> impl Iterator<Item = (LiveThreadId, &mut T)> + '_ {
+        self.buckets.iter_mut().enumerate().flat_map(|(bucket_index, bucket)| {
+            let entries = *bucket.get_mut();
+            let entries: &mut [Entry<T>] = if entries.is_null() {
+                &mut []
+            } else {
+                // SAFETY: The bucket is allocated with the specified size, and we have exclusive access to it
+                unsafe { &mut *ptr::slice_from_raw_parts_mut(entries, 1usize << bucket_index) }
+            };
+            entries.iter_mut().enumerate().filter_map(move |(offset, entry)| {
+                if *entry.present.get_mut() {
+                    // SAFETY: The entry is present
+                    let value = unsafe { entry.value.get_mut().assume_init_mut() };
+                    Some((Location::id_at(bucket_index, offset), value))
+                } else {
+                    None
+                }
+            })
+        })
+    }
+
+    /// Remove all entries from the table, returning them along with their ids.
+    ///
+    /// The entries are ordered by their id.
+    pub(crate) fn take_all(&mut self) -> Vec<(LiveThreadId, T)> {
+        let mut result = Vec::new();
+        for (bucket_index, bucket) in self.buckets.iter_mut().enumerate() {
+            let entries = *bucket.get_mut();
+            if entries.is_null() {
+                continue;
+            }
+            let bucket_size = 1usize << bucket_index;
+            // SAFETY: The bucket is allocated with the specified size,
+            // and we have exclusive access to it
+            let entries = unsafe { &mut *ptr::slice_from_raw_parts_mut(entries, bucket_size) };
+            for (offset, entry) in entries.iter_mut().enumerate() {
+                if let Some(value) = entry.take() {
+                    result.push((Location::id_at(bucket_index, offset), value));
+                }
+            }
+        }
+        result
+    }
+}
+impl<T> Drop for ThreadTable<T> {
+    fn drop(&mut self) {
+        for (bucket_index, bucket) in self.buckets.iter_mut().enumerate() {
+            let entries = *bucket.get_mut();
+            if !entries.is_null() {
+                // SAFETY: The bucket was allocated with the specified size,
+                // and we have exclusive access to it
+                drop(unsafe { Box::from_raw(ptr::slice_from_raw_parts_mut(entries, 1usize << bucket_index)) });
+            }
+        }
+    }
+}
+
+struct Entry<T> {
+    present: AtomicBool,
+    value: UnsafeCell<MaybeUninit<T>>,
+}
+impl<T> Entry<T> {
+    fn new() -> Entry<T> {
+        Entry {
+            present: AtomicBool::new(false),
+            value: UnsafeCell::new(MaybeUninit::uninit()),
+        }
+    }
+
+    fn take(&mut self) -> Option<T> {
+        if core::mem::replace(self.present.get_mut(), false) {
+            // SAFETY: The value was present, and is now marked as absent
+            Some(unsafe { self.value.get_mut().assume_init_read() })
+        } else {
+            None
+        }
+    }
+}
+impl<T> Drop for Entry<T> {
+    fn drop(&mut self) {
+        drop(self.take());
+    }
+}
+
+/// The location of an id within the table.
+#[derive(Copy, Clone)]
+struct Location {
+    bucket: usize,
+    bucket_size: usize,
+    offset: usize,
+}
+impl Location {
+    #[inline]
+    fn of(id: LiveThreadId) -> Location {
+        // cannot overflow, because usize::MAX is not a valid index
+        let key = id.index() + 1;
+        let bucket = (usize::BITS - 1 - key.leading_zeros()) as usize;
+        let bucket_size = 1usize << bucket;
+        Location {
+            bucket,
+            bucket_size,
+            offset: key - bucket_size,
+        }
+    }
+
+    /// The id corresponding to an offset within a bucket.
+    #[inline]
+    fn id_at(bucket: usize, offset: usize) -> LiveThreadId {
+        LiveThreadId {
+            index: NonMaxUsize::new((1usize << bucket) + offset - 1).unwrap(),
+        }
+    }
+}
@@ -7,7 +7,7 @@ macro_rules! fast_thread_local {
                 #[thread_local]
                 static $var: $crate::locals::nightly::NightlyLocalKey<$tp> = $crate::locals::nightly::NightlyLocalKey::new($init);)*
             } else if #[cfg(feature = "std")] {
-                std::thread_local! {
+                ::std::thread_local! {
                     $(
                     $(#[$field_attr])*
                     static $var: $tp = const { $init };)*
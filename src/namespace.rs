@@ -0,0 +1,209 @@
+//! Defines [`ThreadIdNamespace`], which allocates ids independently of the global allocator.
+
+use alloc::sync::{Arc, Weak};
+use alloc::vec::Vec;
+use core::cell::RefCell;
+use core::fmt::{Debug, Display, Formatter};
+use core::num::NonZeroU64;
+
+use portable_atomic::AtomicU64;
+
+use crate::live::ThreadIdAllocator;
+use crate::utils::sync::Mutex;
+use crate::{LiveThreadId, UniqueThreadId};
+
+/// An isolated namespace of thread ids.
+///
+/// Each namespace has its own counter for [`NamespacedUniqueId`]s
+/// and its own allocator for [`NamespacedLiveId`]s,
+/// so ids from one namespace are unrelated to the ids of another namespace
+/// or the process-global ids returned by [`UniqueThreadId::current`] and [`LiveThreadId::current`].
+/// This allows a library to maintain ids which are not affected by other users of this crate.
+///
+/// The ids of a namespace have separate types, which do not implement [`IThreadId`](crate::IThreadId)
+/// and cannot be converted into the global ids,
+/// because the same integer may identify a different thread in the global allocator.
+/// Ids from different namespaces share a type, so they must not be mixed up.
+///
+/// # Performance
+/// The global ids are cached in dedicated thread-locals, so looking them up is a single thread-local read.
+/// Namespaces cannot use dedicated thread-locals,
+/// so each thread instead keeps a list of the namespaces it has used.
+/// Looking up an id requires a linear search of this list,
+/// which is significantly slower than the global fast path.
+/// Allocating the first id on each thread is roughly equivalent in cost.
+///
+/// The [`LiveThreadId`]s of a namespace are released when the thread dies,
+/// as long as the namespace is still alive.
+pub struct ThreadIdNamespace {
+    inner: Arc<NamespaceInner>,
+}
+impl ThreadIdNamespace {
+    /// Create a new namespace, with no ids allocated.
+    #[must_use]
+    pub fn new() -> ThreadIdNamespace {
+        ThreadIdNamespace {
+            inner: Arc::new(NamespaceInner {
+                next_unique_id: AtomicU64::new(1),
+                live_allocator: Mutex::new(ThreadIdAllocator::new()),
+            }),
+        }
+    }
+
+    /// Get the unique id of the currently executing thread within this namespace.
+    ///
+    /// The id is unique among all threads that have ever used this namespace.
+    ///
+    /// May panic if called from a thread destructor.
+    pub fn unique_current(&self) -> NamespacedUniqueId {
+        self.with_entry(|entry| match entry.unique {
+            Some(existing) => existing,
+            None => {
+                let new_id = NamespacedUniqueId(UniqueThreadId::alloc_int_from(&self.inner.next_unique_id));
+                entry.unique = Some(new_id);
+                new_id
+            }
+        })
+    }
+
+    /// Get the live id of the currently executing thread within this namespace.
+    ///
+    /// Ids will be reused once a thread dies.
+    ///
+    /// May panic if called from a thread destructor.
+    pub fn live_current(&self) -> NamespacedLiveId {
+        self.with_entry(|entry| match entry.live {
+            Some(existing) => existing,
+            None => {
+                let new_id = NamespacedLiveId(self.inner.live_allocator.lock().alloc());
+                entry.live = Some(new_id);
+                new_id
+            }
+        })
+    }
+
+    #[inline]
+    fn with_entry<R>(&self, func: impl FnOnce(&mut NamespaceEntry) -> R) -> R {
+        NAMESPACE_LOCALS.with(|locals| {
+            let mut entries = locals.entries.borrow_mut();
+            // A live `Weak` prevents the address from being reused
+            let index = match entries
+                .iter()
+                .position(|entry| Weak::as_ptr(&entry.namespace) == Arc::as_ptr(&self.inner))
+            {
+                Some(index) => index,
+                None => {
+                    // prune entries of dead namespaces
+                    entries.retain(|entry| entry.namespace.strong_count() > 0);
+                    entries.push(NamespaceEntry {
+                        namespace: Arc::downgrade(&self.inner),
+                        unique: None,
+                        live: None,
+                    });
+                    entries.len() - 1
+                }
+            };
+            func(&mut entries[index])
+        })
+    }
+}
+impl Default for ThreadIdNamespace {
+    #[inline]
+    fn default() -> Self {
+        ThreadIdNamespace::new()
+    }
+}
+impl Debug for ThreadIdNamespace {
+    fn fmt(&self, f: &mut Formatter<'_>) -> core::fmt::Result {
+        f.debug_struct("ThreadIdNamespace").finish_non_exhaustive()
+    }
+}
+
+/// A thread id allocated by a [`ThreadIdNamespace`], which is never reused within the namespace.
+///
+/// This is the namespaced equivalent of a [`UniqueThreadId`],
+/// but it is only meaningful within the namespace which allocated it.
+/// It is guaranteed that `Option<NamespacedUniqueId>` has the same representation as `NamespacedUniqueId`.
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Hash, Ord, PartialOrd)]
+#[must_use]
+pub struct NamespacedUniqueId(NonZeroU64);
+impl NamespacedUniqueId {
+    /// Get the integer value of this id.
+    ///
+    /// The first id allocated by a namespace is one.
+    #[inline]
+    #[must_use]
+    pub fn to_int(self) -> u64 {
+        self.0.get()
+    }
+}
+impl Display for NamespacedUniqueId {
+    fn fmt(&self, f: &mut Formatter<'_>) -> core::fmt::Result {
+        write!(f, "{}", self.to_int())
+    }
+}
+
+/// A thread id allocated by a [`ThreadIdNamespace`], which may be reused once the thread dies.
+///
+/// This is the namespaced equivalent of a [`LiveThreadId`],
+/// but it is only meaningful within the namespace which allocated it.
+/// It is guaranteed that `Option<NamespacedLiveId>` has the same representation as `NamespacedLiveId`.
+#[derive(Copy, Clone, Eq, PartialEq, Hash, Ord, PartialOrd)]
+#[must_use]
+pub struct NamespacedLiveId(LiveThreadId);
+impl NamespacedLiveId {
+    /// Get the integer value of this id.
+    ///
+    /// Like [`LiveThreadId::to_int`], the allocator attempts to minimize the value,
+    /// so it is sensible to use it to index a vector.
+    #[inline]
+    #[must_use]
+    pub fn to_int(self) -> usize {
+        self.0.to_int()
+    }
+}
+impl Debug for NamespacedLiveId {
+    fn fmt(&self, f: &mut Formatter<'_>) -> core::fmt::Result {
+        f.debug_tuple("NamespacedLiveId").field(&self.to_int()).finish()
+    }
+}
+impl Display for NamespacedLiveId {
+    fn fmt(&self, f: &mut Formatter<'_>) -> core::fmt::Result {
+        write!(f, "{}", self.to_int())
+    }
+}
+
+struct NamespaceInner {
+    next_unique_id: AtomicU64,
+    live_allocator: Mutex<ThreadIdAllocator>,
+}
+
+std::thread_local! {
+    /// The namespaces used by the current thread.
+    static NAMESPACE_LOCALS: NamespaceLocals = const {
+        NamespaceLocals {
+            entries: RefCell::new(Vec::new()),
+        }
+    };
+}
+/// The size of the thread-locals declared by this module.
+///
+/// This does not include the heap-allocated entries for each namespace.
+pub(crate) const LOCALS_SIZE: usize = core::mem::size_of::<NamespaceLocals>();
+struct NamespaceEntry {
+    namespace: Weak<NamespaceInner>,
+    unique: Option<NamespacedUniqueId>,
+    live: Option<NamespacedLiveId>,
+}
+struct NamespaceLocals {
+    entries: RefCell<Vec<NamespaceEntry>>,
+}
+impl Drop for NamespaceLocals {
+    fn drop(&mut self) {
+        for entry in self.entries.get_mut().drain(..) {
+            if let (Some(namespace), Some(live)) = (entry.namespace.upgrade(), entry.live) {
+                namespace.live_allocator.lock().free(live.0);
+            }
+        }
+    }
+}
@@ -2,6 +2,10 @@
 use core::borrow::Borrow;
 #[cfg(not(feature = "nightly"))]
 use core::cell::Cell;
+use core::cmp::Ordering;
+use core::fmt::{Display, Formatter};
+#[cfg(not(feature = "nightly"))]
+use core::hash::{Hash, Hasher};
 use core::ops::Deref;
 use std::thread::ThreadId;
 
@@ -12,6 +16,12 @@ use equivalent::Equivalent;
 fast_thread_local! {
     static STD_TID: Cell<Option<StdThreadId>> = Cell::new(None);
 }
+/// The size of the thread-locals declared by this module.
+#[cfg(not(feature = "nightly"))]
+pub(crate) const LOCALS_SIZE: usize = core::mem::size_of::<Cell<Option<StdThreadId>>>();
+/// The size of the thread-locals declared by this module.
+#[cfg(feature = "nightly")]
+pub(crate) const LOCALS_SIZE: usize = 0;
 
 /// Wraps the [`std::thread::ThreadId`] type.
 ///
@@ -55,10 +65,32 @@ impl StdThreadId {
 #[cfg_attr(feature = "nightly-docs", doc(cfg(feature = "std")))]
 // SAFETY: Wrapper around std::thread::ThreadId
 unsafe impl crate::IThreadId for StdThreadId {
+    type Raw = ThreadId;
+
     #[inline]
     fn current() -> StdThreadId {
         <Self>::current()
     }
+
+    #[inline]
+    fn to_raw(self) -> ThreadId {
+        self.0
+    }
+
+    #[inline]
+    fn try_current() -> Result<Self, crate::AccessError> {
+        Ok(<Self>::current())
+    }
+
+    #[inline]
+    fn to_nonzero_u128(self) -> Option<core::num::NonZeroU128> {
+        self.0.to_nonzero_u128()
+    }
+
+    #[inline]
+    fn as_u64(&self) -> u64 {
+        crate::IThreadId::as_u64(&self.0)
+    }
 }
 #[cfg(feature = "bytemuck")]
 #[cfg_attr(feature = "nightly-docs", doc(cfg(feature = "bytemuck")))]
@@ -66,12 +98,142 @@ unsafe impl crate::IThreadId for StdThreadId {
 unsafe impl bytemuck::TransparentWrapper<ThreadId> for StdThreadId {}
 // SAFETY: stdlib guarantees that threadid is unique
 unsafe impl crate::IThreadId for ThreadId {
+    type Raw = ThreadId;
+
     #[inline]
     fn current() -> Self {
         StdThreadId::current().0
     }
+
+    #[inline]
+    fn to_raw(self) -> ThreadId {
+        self
+    }
+
+    #[inline]
+    fn try_current() -> Result<Self, crate::AccessError> {
+        Ok(StdThreadId::current().0)
+    }
+
+    #[inline]
+    fn to_nonzero_u128(self) -> Option<core::num::NonZeroU128> {
+        cfg_if! {
+            if #[cfg(feature = "nightly")] {
+                Some(std::thread::ThreadId::as_u64(&self).into())
+            } else {
+                None
+            }
+        }
+    }
+
+    #[inline]
+    fn as_u64(&self) -> u64 {
+        cfg_if! {
+            if #[cfg(feature = "nightly")] {
+                std::thread::ThreadId::as_u64(self).get()
+            } else {
+                let mut hasher = CaptureHasher::default();
+                self.hash(&mut hasher);
+                hasher.finish()
+            }
+        }
+    }
 }
+/// Captures the integer written by the [`Hash`] implementation of [`ThreadId`].
+///
+/// Falls back to hashing any other data, in case the implementation changes.
+#[cfg(not(feature = "nightly"))]
+#[derive(Default)]
+struct CaptureHasher {
+    value: u64,
+}
+#[cfg(not(feature = "nightly"))]
+impl Hasher for CaptureHasher {
+    #[inline]
+    fn finish(&self) -> u64 {
+        self.value
+    }
+
+    fn write(&mut self, bytes: &[u8]) {
+        // FNV-1a
+        for &byte in bytes {
+            self.value = (self.value ^ u64::from(byte)).wrapping_mul(0x0100_0000_01b3);
+        }
+    }
+
+    #[inline]
+    fn write_u64(&mut self, value: u64) {
+        self.value = value;
+    }
+}
+/// Displays the integer value of the id if the `nightly` feature is enabled,
+/// otherwise falls back to the [`Debug`](core::fmt::Debug) output of the [`ThreadId`].
+impl Display for StdThreadId {
+    fn fmt(&self, f: &mut Formatter<'_>) -> core::fmt::Result {
+        cfg_if! {
+            if #[cfg(feature = "nightly")] {
+                write!(f, "{}", self.0.as_u64())
+            } else {
+                write!(f, "{:?}", self.0)
+            }
+        }
+    }
+}
+/// Orders ids by [`std::thread::ThreadId::as_u64`] if the `nightly` feature is enabled.
+///
+/// Otherwise, ids are ordered by the integer written by their [`Hash`] implementation,
+/// which is the same value as [`IThreadId::as_u64`](crate::IThreadId::as_u64).
+/// This does not require any locking, so it is nearly as fast as comparing integers.
+/// If the standard library changes its implementation, distinct ids could produce the same value.
+/// Only those ids fall back to a number assigned from a global counter the first time they are compared,
+/// which requires locking a global map and keeps an entry for each such id for the rest of the program.
+///
+/// In either case, the ordering is consistent within a single execution of the program,
+/// but is not meaningful across executions.
+impl Ord for StdThreadId {
+    #[inline]
+    fn cmp(&self, other: &Self) -> Ordering {
+        if self.0 == other.0 {
+            return Ordering::Equal;
+        }
+        cfg_if! {
+            if #[cfg(feature = "nightly")] {
+                self.0.as_u64().cmp(&other.0.as_u64())
+            } else {
+                let key = |id: &ThreadId| crate::IThreadId::as_u64(id);
+                key(&self.0)
+                    .cmp(&key(&other.0))
+                    .then_with(|| Self::cmp_colliding(self.0, other.0))
+            }
+        }
+    }
+}
+impl PartialOrd for StdThreadId {
+    #[inline]
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+/// The keys used to order each colliding [`ThreadId`], in the order they were first compared.
+#[cfg(not(feature = "nightly"))]
+static ORDER_KEYS: crate::utils::sync::Mutex<Option<std::collections::HashMap<ThreadId, usize>>> =
+    crate::utils::sync::Mutex::new(None);
 impl StdThreadId {
+    /// Order distinct ids whose [`Hash`] implementation wrote the same value.
+    ///
+    /// This is unreachable with the current implementation of the standard library.
+    #[cold]
+    #[cfg(not(feature = "nightly"))]
+    fn cmp_colliding(first: ThreadId, second: ThreadId) -> Ordering {
+        let mut keys = ORDER_KEYS.lock();
+        let keys = keys.get_or_insert_with(std::collections::HashMap::new);
+        let mut key = |id: ThreadId| {
+            let next = keys.len();
+            *keys.entry(id).or_insert(next)
+        };
+        key(first).cmp(&key(second))
+    }
+
     #[cold]
     #[cfg(not(feature = "nightly"))]
     fn acquire() -> StdThreadId {
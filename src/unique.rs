@@ -1,23 +1,59 @@
-//! Defines [`UniqueThreadId`].
+//! Defines [`UniqueThreadId`], [`CompactThreadId`], and [`AtomicUniqueThreadId`].
 
-use core::num::NonZeroU64;
+use core::fmt::{Display, Formatter};
+use core::hash::{Hash, Hasher};
+use core::num::{NonZeroU64, ParseIntError};
+use core::sync::atomic::Ordering;
+
+use equivalent::Equivalent;
+use portable_atomic::AtomicU64;
+
+pub use self::atomic::AtomicUniqueThreadId;
+pub use self::compact::CompactThreadId;
+#[cfg(not(all(feature = "nightly", feature = "std")))]
+use crate::utils::{ColdPath, ReentrancyGuard};
+
+mod atomic;
+mod compact;
 
 fast_thread_local! {
     #[cfg(not(all(feature = "nightly", feature = "std")))]
     static THREAD_ID: core::cell::Cell<Option<UniqueThreadId>> = core::cell::Cell::new(None);
 }
+/// The size of the thread-locals declared by this module.
+#[cfg(not(all(feature = "nightly", feature = "std")))]
+pub(crate) const LOCALS_SIZE: usize =
+    core::mem::size_of::<core::cell::Cell<Option<UniqueThreadId>>>() + compact::LOCALS_SIZE;
+/// The size of the thread-locals declared by this module.
+#[cfg(all(feature = "nightly", feature = "std"))]
+pub(crate) const LOCALS_SIZE: usize = compact::LOCALS_SIZE;
+#[cfg(not(all(feature = "nightly", feature = "std")))]
+static NEXT_ID: AtomicU64 = AtomicU64::new(1);
+/// The counter value at which [`UniqueThreadId::try_alloc_int_from`] reports overflow.
+///
+/// This is far below `u64::MAX`, because `fetch_add` keeps incrementing the counter
+/// even after reporting overflow, and must never wrap back around to zero.
+/// A `fetch_update` could check against `u64::MAX` instead, but would need a compare-exchange loop.
+const OVERFLOW_THRESHOLD: u64 = 1 << 63;
+/// Associates each [`std::thread::ThreadId`] with a [`UniqueThreadId`],
+/// for when the integer value of the standard library's id is inaccessible.
+#[cfg(all(feature = "unique-wrap-std", not(feature = "nightly")))]
+static STD_IDS: crate::utils::sync::Mutex<Option<std::collections::HashMap<std::thread::ThreadId, UniqueThreadId>>> =
+    crate::utils::sync::Mutex::new(None);
 
 /// A globally unique thread id.
 ///
 /// Very similar to [`std::thread::ThreadId`],
 /// except that it has less lookup overhead and gives access to the integer value.
 /// If the `unique-wraps-std` feature is enabled,
-/// the integer values are guaranteed to match the corresponding [`std::thread::ThreadId`].
+/// each id is guaranteed to correspond to a [`std::thread::ThreadId`],
+/// so it can be converted with `UniqueThreadId::from_std`.
+/// On nightly, the integer values are guaranteed to match the corresponding [`std::thread::ThreadId`].
 /// If this feature is not enabled, they may not match.
 ///
 /// While the current value is a [`core::num::NonZero`],
 /// this may change in the future if other niche types like `NonMax` become stabilized.
-#[derive(Copy, Clone, Debug, Eq, PartialEq, Hash, Ord, PartialOrd)]
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Ord, PartialOrd)]
 #[must_use]
 #[repr(transparent)]
 pub struct UniqueThreadId(NonZeroU64);
@@ -30,14 +66,58 @@ impl UniqueThreadId {
     /// It is roughly equivalent to calling [`core::mem::transmute`].
     #[inline]
     pub unsafe fn from_int(x: u64) -> Self {
+        debug_assert_ne!(x, 0, "invalid UniqueThreadId");
         // SAFETY: Caller guarantees that id is valid
         UniqueThreadId(unsafe { NonZeroU64::new_unchecked(x) })
     }
 
+    /// Create a [`UniqueThreadId`] from an integer value, returning `None` if it is zero.
+    ///
+    /// Unlike [`Self::from_int`], passing an invalid integer is not undefined behavior.
+    /// This is still `unsafe`, because code relying on [`IThreadId`](crate::IThreadId)
+    /// may assume that no two threads share an id,
+    /// so an id must not be forged from an arbitrary integer.
+    ///
+    /// ## Safety
+    /// If the integer is nonzero, it must originate from calling [`UniqueThreadId::to_int`]
+    /// in this same program execution.
+    #[inline]
+    #[must_use]
+    pub unsafe fn try_from_int(x: u64) -> Option<Self> {
+        NonZeroU64::new(x).map(UniqueThreadId)
+    }
+
+    /// Parse a [`UniqueThreadId`] from the text produced by its [`Display`] implementation.
+    ///
+    /// This is not exposed as a [`FromStr`](core::str::FromStr) implementation,
+    /// because that would allow safe code to forge an id.
+    ///
+    /// ## Safety
+    /// The same as [`Self::from_int`]:
+    /// the text must originate from formatting a [`UniqueThreadId`] in this same program execution.
+    /// Ids persisted by a previous execution must not be parsed.
+    ///
+    /// ## Errors
+    /// Returns an error if the text is not a valid non-zero `u64`.
+    pub unsafe fn parse(text: &str) -> Result<Self, ParseThreadIdError> {
+        let value = text
+            .parse::<NonZeroU64>()
+            .map_err(|cause| ParseThreadIdError { cause })?;
+        // SAFETY: Caller guarantees that id is valid
+        Ok(unsafe { Self::from_int(value.get()) })
+    }
+
     /// Create a [`UniqueThreadId`] from a [`std::thread::ThreadId`].
     ///
     /// Requires the `unique-wrap-std` feature to be enabled,
     /// because otherwise the thread ids could differ.
+    ///
+    /// Without the `nightly` feature, the integer value of a [`std::thread::ThreadId`] is inaccessible,
+    /// so the ids are associated using a global map.
+    /// This requires acquiring a lock, and the map keeps an entry for every thread which has ever had an id.
+    ///
+    /// ## Panics
+    /// Without the `nightly` feature, panics if the id is new and no more ids can be allocated.
     #[cfg(feature = "unique-wrap-std")]
     #[cfg_attr(feature = "nightly-docs", doc(cfg(feature = "unique-wrap-std")))]
     #[inline]
@@ -47,32 +127,94 @@ impl UniqueThreadId {
                 // SAFETY: Enabling the feature guarantees ids are equivalent
                 unsafe { Self::from_int(id.into().as_u64().get()) }
             } else {
-                let _ = id;
-                unreachable!("unique-wrap-std not possible without nightly features")
+                Self::wrap_std(id.into()).unwrap_or_else(|overflow| panic!("{overflow}"))
             }
         }
     }
 
+    /// Get the id associated with a [`std::thread::ThreadId`], allocating one if needed.
+    #[cold]
+    #[cfg(all(feature = "unique-wrap-std", not(feature = "nightly")))]
+    fn wrap_std(id: std::thread::ThreadId) -> Result<UniqueThreadId, ThreadIdOverflow> {
+        let mut lock = STD_IDS.lock();
+        let ids = lock.get_or_insert_with(std::collections::HashMap::new);
+        if let Some(&existing) = ids.get(&id) {
+            return Ok(existing);
+        }
+        let new_id = Self::try_alloc_from(&NEXT_ID)?;
+        ids.insert(id, new_id);
+        Ok(new_id)
+    }
+
     /// Convert a [`UniqueThreadId`] into an integer value.
     #[inline]
     #[must_use]
     pub fn to_int(&self) -> u64 {
-        self.0.get()
+        let value = self.0.get();
+        debug_assert_ne!(value, 0, "UniqueThreadId niche violated");
+        value
+    }
+
+    /// Assert that the invariants of this id hold.
+    ///
+    /// The niche of the id is relied upon by `Option<UniqueThreadId>` and the serde support,
+    /// so a violation indicates undefined behavior has already occurred,
+    /// typically by passing an invalid value to [`Self::from_int`].
+    /// This is intended as a test aid, and is checked even in release builds.
+    ///
+    /// ## Panics
+    /// If the invariants have been violated.
+    #[track_caller]
+    pub fn debug_check_invariants(self) {
+        assert_ne!(self.0.get(), 0, "UniqueThreadId niche violated");
     }
 
     #[cold]
     #[cfg(not(all(feature = "nightly", feature = "std")))]
-    fn alloc() -> UniqueThreadId {
-        use core::sync::atomic::Ordering;
+    fn try_alloc() -> Result<UniqueThreadId, ThreadIdOverflow> {
+        let _guard = ReentrancyGuard::enter(ColdPath::Unique);
+        cfg_if::cfg_if! {
+            if #[cfg(feature = "unique-wrap-std")] {
+                Self::wrap_std(crate::StdThreadId::current().0)
+            } else {
+                Self::try_alloc_from(&NEXT_ID)
+            }
+        }
+    }
+
+    /// Allocate a new integer id from the specified counter,
+    /// which should be initialized to one.
+    ///
+    /// Panics if the counter has overflowed.
+    #[cold]
+    #[cfg(feature = "std")]
+    pub(crate) fn alloc_int_from(next_id: &AtomicU64) -> NonZeroU64 {
+        Self::try_alloc_int_from(next_id).unwrap_or_else(|overflow| panic!("{overflow}"))
+    }
 
-        use portable_atomic::AtomicU64;
-        static NEXT_ID: AtomicU64 = AtomicU64::new(1);
-        let id = NEXT_ID
-            .fetch_update(Ordering::AcqRel, Ordering::Relaxed, |old_value| {
-                old_value.checked_add(1)
-            })
-            .expect("id overflow");
-        UniqueThreadId(NonZeroU64::new(id).unwrap())
+    /// Allocate a new id from the specified counter,
+    /// returning an error if it has overflowed.
+    #[cold]
+    #[cfg(not(all(feature = "nightly", feature = "std")))]
+    pub(crate) fn try_alloc_from(next_id: &AtomicU64) -> Result<UniqueThreadId, ThreadIdOverflow> {
+        Self::try_alloc_int_from(next_id).map(UniqueThreadId)
+    }
+
+    /// Allocate a new integer id from the specified counter,
+    /// returning an error if it has overflowed.
+    #[cold]
+    fn try_alloc_int_from(next_id: &AtomicU64) -> Result<NonZeroU64, ThreadIdOverflow> {
+        // Relaxed is sufficient, because the counter is only used for uniqueness,
+        // which is guaranteed by the atomicity of the increment regardless of ordering.
+        // No other memory is published through the counter.
+        let id = next_id.fetch_add(1, Ordering::Relaxed);
+        // Once past the threshold, every caller fails without using its id.
+        // Wrapping around would require 2^63 further calls, so it cannot happen in practice.
+        // Zero is only possible if the counter was corrupted, so it also reports overflow.
+        match NonZeroU64::new(id) {
+            Some(id) if id.get() < OVERFLOW_THRESHOLD => Ok(id),
+            _ => Err(ThreadIdOverflow),
+        }
     }
 
     /// Get the thread id of the currently executing thread.
@@ -81,36 +223,186 @@ impl UniqueThreadId {
     /// even if the current thread dies.
     ///
     /// If the `unique-wrap-std` feature is enabled,
-    /// the id is guaranteed to match the corresponding [`std::thread::ThreadId`].
+    /// the id is guaranteed to match the corresponding [`std::thread::ThreadId`],
+    /// as described by `UniqueThreadId::from_std`.
+    ///
+    /// ## Panics
+    /// Panics if no more ids can be allocated, as described by [`UniqueThreadId::try_current`].
     #[inline]
     pub fn current() -> UniqueThreadId {
         cfg_if::cfg_if! {
             if #[cfg(all(feature = "std", feature = "nightly"))] {
-                UniqueThreadId(crate::StdThreadId::current().0.as_u64())
-            } else if #[cfg(feature = "unique-wrap-std")] {
-                compile_error!("The `unique-wrap-std` feature requires the `nightly` feature to be enabled")
+                crate::StdThreadId::current().into()
+            } else {
+                THREAD_ID.with(Self::get_or_alloc).unwrap_or_else(|overflow| panic!("{overflow}"))
+            }
+        }
+    }
+
+    /// Get the thread id of the currently executing thread,
+    /// returning an error instead of panicking if all possible ids have been exhausted.
+    ///
+    /// Exhausting the ids requires allocating 2<sup>63</sup> ids, so this should never happen in practice.
+    /// Only half of the `u64` range is used, so that the counter can keep incrementing
+    /// after overflow has been reported without wrapping back around to zero.
+    ///
+    /// ## Errors
+    /// Returns an error if the current thread has not yet been assigned an id,
+    /// and no more ids can be allocated.
+    #[inline]
+    pub fn try_current() -> Result<UniqueThreadId, ThreadIdOverflow> {
+        cfg_if::cfg_if! {
+            if #[cfg(all(feature = "std", feature = "nightly"))] {
+                Ok(Self::current())
+            } else {
+                THREAD_ID.with(Self::get_or_alloc)
+            }
+        }
+    }
+
+    /// Get the id of the current thread,
+    /// returning `None` if the thread-local holding it cannot be accessed.
+    ///
+    /// This never panics, so it is safe to call from [`Drop`] implementations during thread teardown.
+    /// The fast path is the same single thread-local read as [`Self::current`].
+    #[inline]
+    #[must_use]
+    pub fn current_checked() -> Option<UniqueThreadId> {
+        cfg_if::cfg_if! {
+            if #[cfg(all(feature = "std", feature = "nightly"))] {
+                Some(Self::current())
+            } else {
+                THREAD_ID.try_with(Self::get_or_alloc).ok().and_then(Result::ok)
+            }
+        }
+    }
+
+    /// Check if this is the id of the currently executing thread.
+    ///
+    /// If the current thread has not yet been assigned an id,
+    /// this returns `false` without allocating one.
+    #[inline]
+    #[must_use]
+    pub fn is_current(self) -> bool {
+        cfg_if::cfg_if! {
+            if #[cfg(all(feature = "std", feature = "nightly"))] {
+                self == Self::current()
             } else {
-                THREAD_ID.with(|cell| {
-                    match cell.get() {
-                        None => {
-                            let id = UniqueThreadId::alloc();
-                            cell.set(Some(id));
-                            id
-                        }
-                        Some(id) => id,
-                    }
-                })
+                THREAD_ID.try_with(core::cell::Cell::get).ok().flatten() == Some(self)
+            }
+        }
+    }
+
+    #[inline]
+    #[cfg(not(all(feature = "nightly", feature = "std")))]
+    fn get_or_alloc(cell: &core::cell::Cell<Option<UniqueThreadId>>) -> Result<UniqueThreadId, ThreadIdOverflow> {
+        match cell.get() {
+            None => {
+                let id = UniqueThreadId::try_alloc()?;
+                cell.set(Some(id));
+                Ok(id)
             }
+            Some(id) => Ok(id),
         }
     }
 }
+/// Reset the id counter and the id of the current thread.
+///
+/// Does nothing to the [`UniqueThreadId`] if the ids wrap [`std::thread::ThreadId`],
+/// but always resets the [`CompactThreadId`].
+///
+/// ## Safety
+/// See [`crate::reset_all_for_testing`].
+#[cfg(feature = "testing")]
+pub(crate) unsafe fn reset_for_testing() {
+    // SAFETY: Guaranteed by the caller
+    unsafe {
+        compact::reset_for_testing();
+    }
+    #[cfg(not(all(feature = "nightly", feature = "std")))]
+    {
+        let _ = THREAD_ID.try_with(|cell| cell.set(None));
+        #[cfg(feature = "unique-wrap-std")]
+        {
+            *STD_IDS.lock() = None;
+        }
+        NEXT_ID.store(1, Ordering::Release);
+    }
+}
+
+/// Set the value of the first [`UniqueThreadId`] to be allocated,
+/// reserving the range of ids below it.
+///
+/// This is useful to avoid colliding with externally-assigned ids which share the same namespace.
+/// It must be called before any id has been allocated, including by other threads.
+/// If `base` is 2<sup>63</sup> or larger, the ids are immediately exhausted.
+///
+/// Only available if the `nightly` and `std` features are not both enabled,
+/// because the ids are otherwise derived from [`std::thread::ThreadId`].
+///
+/// ## Errors
+/// Returns an error if any id has already been allocated.
+#[cfg(not(all(feature = "nightly", feature = "std")))]
+pub fn set_id_base(base: NonZeroU64) -> Result<(), IdBaseError> {
+    NEXT_ID
+        .compare_exchange(1, base.get(), Ordering::AcqRel, Ordering::Acquire)
+        .map(|_| ())
+        .map_err(|_| IdBaseError)
+}
+
+/// Set the value of the counter used to allocate the next [`UniqueThreadId`],
+/// for testing the handling of overflow.
+///
+/// ## Safety
+/// Ids which have already been allocated must not be allocated again,
+/// so the counter may only be increased.
+#[cfg(all(feature = "testing", not(all(feature = "nightly", feature = "std"))))]
+#[cfg_attr(feature = "nightly-docs", doc(cfg(feature = "testing")))]
+pub unsafe fn set_next_id_for_testing(next_id: u64) {
+    NEXT_ID.store(next_id, Ordering::Release);
+}
+
+/// Set the value of the counter used to allocate the next [`CompactThreadId`],
+/// for testing the handling of overflow.
+///
+/// ## Safety
+/// Ids which have already been allocated must not be allocated again,
+/// so the counter may only be increased.
+#[cfg(feature = "testing")]
+#[cfg_attr(feature = "nightly-docs", doc(cfg(feature = "testing")))]
+pub unsafe fn set_next_compact_id_for_testing(next_id: u32) {
+    // SAFETY: Guaranteed by the caller
+    unsafe { compact::set_next_id_for_testing(next_id) }
+}
 simple_serde_serialize!(UniqueThreadId, |this| this.to_int());
 // SAFETY: Unique across all threads that have ever existed
 unsafe impl crate::IThreadId for UniqueThreadId {
+    type Raw = NonZeroU64;
+
     #[inline]
     fn current() -> Self {
         <Self>::current()
     }
+
+    #[inline]
+    fn to_raw(self) -> NonZeroU64 {
+        self.0
+    }
+
+    #[inline]
+    fn try_current() -> Result<Self, crate::AccessError> {
+        Ok(<Self>::try_current()?)
+    }
+
+    #[inline]
+    fn to_nonzero_u128(self) -> Option<core::num::NonZeroU128> {
+        Some(self.0.into())
+    }
+
+    #[inline]
+    fn as_u64(&self) -> u64 {
+        self.to_int()
+    }
 }
 #[cfg(feature = "bytemuck")]
 #[cfg_attr(feature = "nightly-docs", doc(cfg(feature = "bytemuck")))]
@@ -120,12 +412,151 @@ unsafe impl bytemuck::ZeroableInOption for UniqueThreadId {}
 #[cfg_attr(feature = "nightly-docs", doc(cfg(feature = "bytemuck")))]
 // SAFETY: Wraps a NonZero
 unsafe impl bytemuck::NoUninit for UniqueThreadId {}
+/// Allows safely reading ids from untrusted bytes with [`bytemuck::checked`],
+/// which rejects the zero bit pattern.
+///
+/// The resulting id is only meaningful if it was created in the current process.
+#[cfg(feature = "bytemuck")]
+#[cfg_attr(feature = "nightly-docs", doc(cfg(feature = "bytemuck")))]
+// SAFETY: Wraps a NonZero, so every nonzero u64 is a valid bit pattern
+unsafe impl bytemuck::CheckedBitPattern for UniqueThreadId {
+    type Bits = u64;
+
+    #[inline]
+    fn is_valid_bit_pattern(bits: &u64) -> bool {
+        *bits != 0
+    }
+}
+/// The error returned when all possible [`UniqueThreadId`]s or [`CompactThreadId`]s have been exhausted.
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Hash)]
+pub struct ThreadIdOverflow;
+impl Display for ThreadIdOverflow {
+    fn fmt(&self, f: &mut Formatter<'_>) -> core::fmt::Result {
+        f.write_str("id overflow")
+    }
+}
+#[cfg(feature = "std")]
+#[cfg_attr(feature = "nightly-docs", doc(cfg(feature = "std")))]
+impl std::error::Error for ThreadIdOverflow {}
+impl From<ThreadIdOverflow> for crate::AccessError {
+    #[inline]
+    fn from(_: ThreadIdOverflow) -> Self {
+        crate::AccessError::Overflow
+    }
+}
+/// The error returned by [`set_id_base`] when an id has already been allocated.
+#[cfg(not(all(feature = "nightly", feature = "std")))]
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Hash)]
+pub struct IdBaseError;
+#[cfg(not(all(feature = "nightly", feature = "std")))]
+impl Display for IdBaseError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> core::fmt::Result {
+        f.write_str("id base set after an id was allocated")
+    }
+}
+#[cfg(all(feature = "std", not(feature = "nightly")))]
+impl std::error::Error for IdBaseError {}
+/// The error returned when parsing a [`UniqueThreadId`] fails.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct ParseThreadIdError {
+    cause: ParseIntError,
+}
+impl Display for ParseThreadIdError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> core::fmt::Result {
+        write!(f, "invalid thread id: {}", self.cause)
+    }
+}
+#[cfg(feature = "std")]
+#[cfg_attr(feature = "nightly-docs", doc(cfg(feature = "std")))]
+impl std::error::Error for ParseThreadIdError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        Some(&self.cause)
+    }
+}
+/// Hashes the value of [`UniqueThreadId::to_int`] using a single call to [`Hasher::write_u64`],
+/// so the id can be its own hash.
+impl Hash for UniqueThreadId {
+    #[inline]
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        state.write_u64(self.to_int());
+    }
+}
+impl Display for UniqueThreadId {
+    fn fmt(&self, f: &mut Formatter<'_>) -> core::fmt::Result {
+        write!(f, "{}", self.to_int())
+    }
+}
+/// Formats the value of [`UniqueThreadId::to_int`], respecting all formatting flags.
+impl core::fmt::LowerHex for UniqueThreadId {
+    fn fmt(&self, f: &mut Formatter<'_>) -> core::fmt::Result {
+        core::fmt::LowerHex::fmt(&self.to_int(), f)
+    }
+}
+/// Formats the value of [`UniqueThreadId::to_int`], respecting all formatting flags.
+impl core::fmt::UpperHex for UniqueThreadId {
+    fn fmt(&self, f: &mut Formatter<'_>) -> core::fmt::Result {
+        core::fmt::UpperHex::fmt(&self.to_int(), f)
+    }
+}
+impl PartialEq<u64> for UniqueThreadId {
+    #[inline]
+    fn eq(&self, other: &u64) -> bool {
+        self.to_int() == *other
+    }
+}
+impl PartialEq<UniqueThreadId> for u64 {
+    #[inline]
+    fn eq(&self, other: &UniqueThreadId) -> bool {
+        *self == other.to_int()
+    }
+}
+/// Allows looking up an integer in a map keyed by [`UniqueThreadId`].
+///
+/// This is consistent with [`Hash`], which hashes the same integer.
+impl Equivalent<UniqueThreadId> for u64 {
+    #[inline]
+    fn equivalent(&self, key: &UniqueThreadId) -> bool {
+        *self == key.to_int()
+    }
+}
+/// Allows looking up a [`UniqueThreadId`] in a map keyed by integers.
+impl Equivalent<u64> for UniqueThreadId {
+    #[inline]
+    fn equivalent(&self, key: &u64) -> bool {
+        self.to_int() == *key
+    }
+}
 impl From<UniqueThreadId> for u64 {
     #[inline]
     fn from(value: UniqueThreadId) -> Self {
         value.to_int()
     }
 }
+/// Converts an id which has already been fetched, without looking up the current thread again.
+///
+/// Only available if the `nightly` and `std` features are enabled,
+/// because [`UniqueThreadId::current`] is then derived from [`std::thread::ThreadId::as_u64`].
+/// Otherwise the ids are not guaranteed to be equivalent.
+#[cfg(all(feature = "nightly", feature = "std"))]
+#[cfg_attr(feature = "nightly-docs", doc(cfg(all(feature = "nightly", feature = "std"))))]
+impl From<std::thread::ThreadId> for UniqueThreadId {
+    #[inline]
+    fn from(value: std::thread::ThreadId) -> Self {
+        UniqueThreadId(value.as_u64())
+    }
+}
+/// Converts an id which has already been fetched, without looking up the current thread again.
+///
+/// Only available if the `nightly` and `std` features are enabled,
+/// for the same reason as the conversion from [`std::thread::ThreadId`].
+#[cfg(all(feature = "nightly", feature = "std"))]
+#[cfg_attr(feature = "nightly-docs", doc(cfg(all(feature = "nightly", feature = "std"))))]
+impl From<crate::StdThreadId> for UniqueThreadId {
+    #[inline]
+    fn from(value: crate::StdThreadId) -> Self {
+        value.0.into()
+    }
+}
 #[cfg(feature = "slog")]
 #[cfg_attr(feature = "nightly-docs", doc(cfg(feature = "slog")))]
 impl slog::Value for UniqueThreadId {
@@ -0,0 +1,119 @@
+//! Defines [`AtomicUniqueThreadId`].
+
+use core::fmt::{Debug, Formatter};
+use core::num::NonZeroU64;
+use core::sync::atomic::Ordering;
+
+use portable_atomic::AtomicU64;
+
+use super::UniqueThreadId;
+
+/// An optional [`UniqueThreadId`] which can be shared between threads.
+///
+/// This is useful for recording which thread owns a resource,
+/// for example to detect reentrant locking without a [`Mutex`](::std::sync::Mutex).
+/// The id is stored as a [`u64`], where zero represents `None`.
+/// Ids can never be zero, so this is unambiguous.
+#[repr(transparent)]
+pub struct AtomicUniqueThreadId(AtomicU64);
+impl AtomicUniqueThreadId {
+    /// Create a new atomic id with the specified initial value.
+    #[inline]
+    #[must_use]
+    pub const fn new(id: Option<UniqueThreadId>) -> Self {
+        AtomicUniqueThreadId(AtomicU64::new(Self::to_raw(id)))
+    }
+
+    /// Load the current value.
+    ///
+    /// See [`AtomicU64::load`] for the meaning of the ordering.
+    #[inline]
+    #[must_use]
+    pub fn load(&self, order: Ordering) -> Option<UniqueThreadId> {
+        Self::from_raw(self.0.load(order))
+    }
+
+    /// Store a new value.
+    ///
+    /// See [`AtomicU64::store`] for the meaning of the ordering.
+    #[inline]
+    pub fn store(&self, id: Option<UniqueThreadId>, order: Ordering) {
+        self.0.store(Self::to_raw(id), order);
+    }
+
+    /// Store a new value, returning the previous value.
+    ///
+    /// See [`AtomicU64::swap`] for the meaning of the ordering.
+    #[inline]
+    pub fn swap(&self, id: Option<UniqueThreadId>, order: Ordering) -> Option<UniqueThreadId> {
+        Self::from_raw(self.0.swap(Self::to_raw(id), order))
+    }
+
+    /// Store a new value if the current value is the same as `current`.
+    ///
+    /// See [`AtomicU64::compare_exchange`] for the meaning of the orderings.
+    ///
+    /// ## Errors
+    /// If the value was not equal to `current`, returns the actual value without modifying it.
+    #[inline]
+    pub fn compare_exchange(
+        &self,
+        current: Option<UniqueThreadId>,
+        new: Option<UniqueThreadId>,
+        success: Ordering,
+        failure: Ordering,
+    ) -> Result<Option<UniqueThreadId>, Option<UniqueThreadId>> {
+        self.0
+            .compare_exchange(Self::to_raw(current), Self::to_raw(new), success, failure)
+            .map(Self::from_raw)
+            .map_err(Self::from_raw)
+    }
+
+    /// Store the id of the current thread, returning the previous value.
+    ///
+    /// Equivalent to calling [`Self::swap`] with [`UniqueThreadId::current`].
+    #[inline]
+    pub fn set_current(&self, order: Ordering) -> Option<UniqueThreadId> {
+        self.swap(Some(UniqueThreadId::current()), order)
+    }
+
+    /// Consume the atomic, returning the contained value.
+    #[inline]
+    #[must_use]
+    pub fn into_inner(self) -> Option<UniqueThreadId> {
+        Self::from_raw(self.0.into_inner())
+    }
+
+    #[inline]
+    const fn to_raw(id: Option<UniqueThreadId>) -> u64 {
+        match id {
+            Some(id) => id.0.get(),
+            None => 0,
+        }
+    }
+
+    #[inline]
+    fn from_raw(raw: u64) -> Option<UniqueThreadId> {
+        // only values produced by to_raw are ever stored
+        NonZeroU64::new(raw).map(UniqueThreadId)
+    }
+}
+impl Default for AtomicUniqueThreadId {
+    #[inline]
+    fn default() -> Self {
+        AtomicUniqueThreadId::new(None)
+    }
+}
+impl From<Option<UniqueThreadId>> for AtomicUniqueThreadId {
+    #[inline]
+    fn from(id: Option<UniqueThreadId>) -> Self {
+        AtomicUniqueThreadId::new(id)
+    }
+}
+impl Debug for AtomicUniqueThreadId {
+    fn fmt(&self, f: &mut Formatter<'_>) -> core::fmt::Result {
+        f.debug_tuple("AtomicUniqueThreadId")
+            .field(&self.load(Ordering::Relaxed))
+            .finish()
+    }
+}
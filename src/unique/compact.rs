@@ -0,0 +1,171 @@
+//! Defines [`CompactThreadId`].
+
+use core::cell::Cell;
+use core::fmt::{Display, Formatter};
+use core::num::NonZeroU32;
+use core::sync::atomic::Ordering;
+
+use portable_atomic::AtomicU32;
+
+use super::ThreadIdOverflow;
+use crate::utils::{ColdPath, ReentrancyGuard};
+
+fast_thread_local! {
+    static COMPACT_ID: Cell<Option<CompactThreadId>> = Cell::new(None);
+}
+/// The size of the thread-locals declared by this module.
+pub(crate) const LOCALS_SIZE: usize = core::mem::size_of::<Cell<Option<CompactThreadId>>>();
+static NEXT_ID: AtomicU32 = AtomicU32::new(1);
+
+/// A globally unique thread id, which only uses 32 bits.
+///
+/// This is equivalent to a [`UniqueThreadId`](crate::UniqueThreadId),
+/// except that it is half the size, which is useful in structures which are replicated many times.
+/// The ids are allocated from an independent counter,
+/// so they are unrelated to the values of any other id type.
+///
+/// Only 2<sup>32</sup> - 2 ids can ever be allocated, because zero and `u32::MAX` are never used.
+/// Requesting an id once they are exhausted will panic,
+/// so this type should only be used if the program will never spawn billions of threads.
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Hash, Ord, PartialOrd)]
+#[must_use]
+#[repr(transparent)]
+pub struct CompactThreadId(NonZeroU32);
+impl CompactThreadId {
+    /// Get the thread id of the currently executing thread.
+    ///
+    /// Will never be used by another thread,
+    /// even if the current thread dies.
+    ///
+    /// ## Panics
+    /// If all possible ids have been exhausted, as described by [`CompactThreadId::try_current`].
+    #[inline]
+    pub fn current() -> CompactThreadId {
+        Self::try_current().unwrap_or_else(|_| panic!("CompactThreadId overflowed a u32"))
+    }
+
+    /// Get the thread id of the currently executing thread,
+    /// returning an error instead of panicking if all possible ids have been exhausted.
+    ///
+    /// Unlike a [`UniqueThreadId`](crate::UniqueThreadId),
+    /// a program which spawns billions of threads could realistically exhaust the ids.
+    ///
+    /// ## Errors
+    /// Returns an error if the current thread has not yet been assigned an id,
+    /// and no more ids can be allocated.
+    #[inline]
+    pub fn try_current() -> Result<CompactThreadId, ThreadIdOverflow> {
+        COMPACT_ID.with(|cell| match cell.get() {
+            Some(existing) => Ok(existing),
+            None => {
+                let id = CompactThreadId::try_alloc()?;
+                cell.set(Some(id));
+                Ok(id)
+            }
+        })
+    }
+
+    /// Convert a [`CompactThreadId`] into an integer value.
+    #[inline]
+    #[must_use]
+    pub fn to_int(&self) -> u32 {
+        self.0.get()
+    }
+
+    #[cold]
+    fn try_alloc() -> Result<CompactThreadId, ThreadIdOverflow> {
+        let _guard = ReentrancyGuard::enter(ColdPath::Compact);
+        let id = NEXT_ID
+            .fetch_update(Ordering::AcqRel, Ordering::Relaxed, |old_value| {
+                old_value.checked_add(1)
+            })
+            .map_err(|_| ThreadIdOverflow)?;
+        NonZeroU32::new(id).map(CompactThreadId).ok_or(ThreadIdOverflow)
+    }
+}
+/// Reset the id counter and the id of the current thread.
+///
+/// ## Safety
+/// See [`crate::reset_all_for_testing`].
+#[cfg(feature = "testing")]
+pub(crate) unsafe fn reset_for_testing() {
+    let _ = COMPACT_ID.try_with(|cell| cell.set(None));
+    NEXT_ID.store(1, Ordering::Release);
+}
+/// Set the value of the id counter.
+///
+/// ## Safety
+/// See [`super::set_next_compact_id_for_testing`].
+#[cfg(feature = "testing")]
+pub(crate) unsafe fn set_next_id_for_testing(next_id: u32) {
+    NEXT_ID.store(next_id, Ordering::Release);
+}
+simple_serde_serialize!(CompactThreadId, |this| this.to_int());
+// SAFETY: Unique across all threads that have ever existed
+unsafe impl crate::IThreadId for CompactThreadId {
+    type Raw = NonZeroU32;
+
+    #[inline]
+    fn current() -> Self {
+        <Self>::current()
+    }
+
+    #[inline]
+    fn to_raw(self) -> NonZeroU32 {
+        self.0
+    }
+
+    #[inline]
+    fn try_current() -> Result<Self, crate::AccessError> {
+        Ok(<Self>::try_current()?)
+    }
+
+    #[inline]
+    fn to_nonzero_u128(self) -> Option<core::num::NonZeroU128> {
+        Some(self.0.into())
+    }
+
+    #[inline]
+    fn as_u64(&self) -> u64 {
+        self.to_int().into()
+    }
+}
+#[cfg(feature = "bytemuck")]
+#[cfg_attr(feature = "nightly-docs", doc(cfg(feature = "bytemuck")))]
+// SAFETY: Wraps a NonZero
+unsafe impl bytemuck::ZeroableInOption for CompactThreadId {}
+#[cfg(feature = "bytemuck")]
+#[cfg_attr(feature = "nightly-docs", doc(cfg(feature = "bytemuck")))]
+// SAFETY: Wraps a NonZero
+unsafe impl bytemuck::NoUninit for CompactThreadId {}
+/// Allows safely reading ids from untrusted bytes with [`bytemuck::checked`],
+/// which rejects the zero bit pattern.
+#[cfg(feature = "bytemuck")]
+#[cfg_attr(feature = "nightly-docs", doc(cfg(feature = "bytemuck")))]
+// SAFETY: Wraps a NonZero, so every nonzero u32 is a valid bit pattern
+unsafe impl bytemuck::CheckedBitPattern for CompactThreadId {
+    type Bits = u32;
+
+    #[inline]
+    fn is_valid_bit_pattern(bits: &u32) -> bool {
+        *bits != 0
+    }
+}
+impl Display for CompactThreadId {
+    fn fmt(&self, f: &mut Formatter<'_>) -> core::fmt::Result {
+        write!(f, "{}", self.to_int())
+    }
+}
+impl From<CompactThreadId> for u32 {
+    #[inline]
+    fn from(value: CompactThreadId) -> Self {
+        value.to_int()
+    }
+}
+#[cfg(feature = "slog")]
+#[cfg_attr(feature = "nightly-docs", doc(cfg(feature = "slog")))]
+impl slog::Value for CompactThreadId {
+    fn serialize(&self, _record: &slog::Record, key: slog::Key, serializer: &mut dyn slog::Serializer) -> slog::Result {
+        serializer.emit_u32(key, self.to_int())
+    }
+}
@@ -22,56 +22,78 @@ pub mod sync {
     }
 }
 
-#[cfg_attr(not(feature = "std"), allow(unused_imports))]
-pub use self::cell::OnceCell;
-#[cfg_attr(not(feature = "std"), allow(dead_code))]
-mod cell {
-    use core::cell::UnsafeCell;
+#[cfg(debug_assertions)]
+fast_thread_local! {
+    /// The set of [`ColdPath`]s which the current thread is inside, as a bitmask.
+    static IN_COLD_PATH: core::cell::Cell<u8> = core::cell::Cell::new(0);
+}
+/// The size of the thread-locals declared by this module.
+#[cfg(debug_assertions)]
+pub(crate) const LOCALS_SIZE: usize = core::mem::size_of::<core::cell::Cell<u8>>();
+/// The size of the thread-locals declared by this module.
+#[cfg(not(debug_assertions))]
+pub(crate) const LOCALS_SIZE: usize = 0;
 
-    /// A version of [`std::cell::OnceCell`] that supports our MSRV.
-    ///
-    /// NOTE: The `once_lock` crate has a newer MSRV than we do,
-    /// and gives rather poor MSRV guarantees.
-    pub struct OnceCell<T> {
-        value: UnsafeCell<Option<T>>,
-    }
-    impl<T> OnceCell<T> {
-        #[inline]
-        pub const fn new() -> Self {
-            OnceCell {
-                value: UnsafeCell::new(None),
-            }
-        }
+/// The cold paths of each allocator, which are tracked independently by [`ReentrancyGuard`].
+///
+/// Entering the cold path of one allocator from another is harmless,
+/// because they do not share any state.
+/// Each variant is only used when its allocator has a cold path in the enabled features.
+#[derive(Copy, Clone)]
+#[allow(dead_code)]
+pub enum ColdPath {
+    /// Allocating or freeing a [`LiveThreadId`](crate::LiveThreadId).
+    Live = 1 << 0,
+    /// Allocating a [`UniqueThreadId`](crate::UniqueThreadId).
+    Unique = 1 << 1,
+    /// Allocating a [`CompactThreadId`](crate::unique::CompactThreadId).
+    Compact = 1 << 2,
+}
 
-        #[inline]
-        pub fn get(&self) -> Option<&'_ T> {
-            // SAFETY: Guaranteed to only be initialized once
-            unsafe {
-                let x: &Option<T> = &*self.value.get();
-                x.as_ref()
-            }
+/// Detects recursive entry into the cold paths which allocate and free ids.
+///
+/// Recursion could otherwise deadlock or corrupt the state of the allocator,
+/// for example if a global allocator calls `current()`.
+/// The check is only performed in debug builds, and compiles to nothing in release builds.
+#[must_use]
+pub struct ReentrancyGuard {
+    #[cfg(debug_assertions)]
+    path: ColdPath,
+}
+impl ReentrancyGuard {
+    /// Mark the current thread as being inside the specified cold path,
+    /// panicking if it is already inside it.
+    #[inline]
+    #[cfg_attr(not(debug_assertions), allow(unused_variables))]
+    pub fn enter(path: ColdPath) -> ReentrancyGuard {
+        #[cfg(debug_assertions)]
+        {
+            let _ = IN_COLD_PATH.try_with(|flags| {
+                let old = flags.get();
+                assert!(
+                    old & path as u8 == 0,
+                    "thread id allocated recursively (was `current()` called while allocating an id?)"
+                );
+                flags.set(old | path as u8);
+            });
         }
-
-        #[inline]
-        pub fn set(&self, new_value: T) -> Result<(), &'_ T> {
-            // SAFETY: Upholds the invariant that initialization is only done once
-            // Cannot be called from multiple threads, since !Sync,
-            // and cannot be called recursively because there is no callback
-            unsafe {
-                let x: &mut Option<T> = &mut *self.value.get();
-                match *x {
-                    Some(ref existing) => Err(existing),
-                    None => {
-                        *x = Some(new_value);
-                        Ok(())
-                    }
-                }
-            }
+        ReentrancyGuard {
+            #[cfg(debug_assertions)]
+            path,
         }
     }
-    // SAFETY: Fine to send because old thread loses access
-    unsafe impl<T> Send for OnceCell<T> {}
 }
+impl Drop for ReentrancyGuard {
+    #[inline]
+    fn drop(&mut self) {
+        #[cfg(debug_assertions)]
+        {
+            let path = self.path as u8;
+            let _ = IN_COLD_PATH.try_with(|flags| flags.set(flags.get() & !path));
+        }
+    }
+}
+
 macro_rules! simple_serde_serialize {
     ($target:ident, |$this:ident| $to_inner:expr) => {
         #[cfg(feature = "serde")]
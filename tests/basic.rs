@@ -81,3 +81,496 @@ fn death_reuse() {
     })
     .unwrap();
 }
+
+#[test]
+fn thread_sequence() {
+    let first = threadid::current_seq();
+    let second = threadid::current_seq();
+    assert!(first < second);
+    thread::scope(|scope| {
+        scope
+            .spawn(|_scope| {
+                // independent of the other thread
+                assert_eq!(threadid::current_seq(), 0);
+                assert_eq!(threadid::current_seq(), 1);
+            })
+            .join()
+            .unwrap();
+    })
+    .unwrap();
+    assert!(threadid::current_seq() > second);
+}
+
+#[test]
+fn per_thread_overhead() {
+    let overhead = threadid::per_thread_overhead();
+    assert_eq!(overhead, threadid::PER_THREAD_OVERHEAD);
+    assert!(overhead > 0);
+    assert!(overhead < 1024, "unreasonable overhead: {overhead}");
+}
+
+#[test]
+fn check_invariants() {
+    UniqueThreadId::current().debug_check_invariants();
+    #[cfg(feature = "std")]
+    {
+        LiveThreadId::current().debug_check_invariants();
+        LiveThreadId::SENTINEL.debug_check_invariants();
+    }
+}
+
+#[test]
+#[cfg(debug_assertions)]
+#[should_panic(expected = "invalid UniqueThreadId")]
+fn invalid_unique_from_int() {
+    // SAFETY: The debug assertion panics before the invalid id is created
+    let _ = unsafe { UniqueThreadId::from_int(0) };
+}
+
+#[test]
+fn try_from_int() {
+    let unique = UniqueThreadId::current();
+    // SAFETY: The integer originates from a valid id, and invalid integers are rejected
+    unsafe {
+        assert_eq!(UniqueThreadId::try_from_int(unique.to_int()), Some(unique));
+        assert_eq!(UniqueThreadId::try_from_int(0), None);
+    }
+    #[cfg(feature = "std")]
+    {
+        let live = LiveThreadId::current();
+        // SAFETY: The integer originates from the id of the current thread, and invalid integers are rejected
+        unsafe {
+            assert_eq!(LiveThreadId::try_from_int(live.to_int()), Some(live));
+            assert_eq!(LiveThreadId::try_from_int(usize::MAX), None);
+        }
+    }
+}
+
+#[test]
+fn display() {
+    let unique = UniqueThreadId::current();
+    assert_eq!(unique.to_string(), unique.to_int().to_string());
+    #[cfg(feature = "std")]
+    {
+        let live = LiveThreadId::current();
+        assert_eq!(live.to_string(), live.index().to_string());
+        assert!(!StdThreadId::current().to_string().is_empty());
+    }
+}
+
+#[test]
+fn hex() {
+    let unique = UniqueThreadId::current();
+    let value = unique.to_int();
+    assert_eq!(format!("{unique:x}"), format!("{value:x}"));
+    assert_eq!(format!("{unique:#X}"), format!("{value:#X}"));
+    assert_eq!(format!("{unique:016x}"), format!("{value:016x}"));
+    assert_eq!(format!("{unique:#018X}"), format!("{value:#018X}"));
+    assert_eq!(format!("{unique:>8x}").len(), 8.max(format!("{value:x}").len()));
+    #[cfg(feature = "std")]
+    {
+        let live = LiveThreadId::current();
+        let value = live.to_int();
+        assert_eq!(format!("{live:x}"), format!("{value:x}"));
+        assert_eq!(format!("{live:#X}"), format!("{value:#X}"));
+        assert_eq!(format!("{live:08x}"), format!("{value:08x}"));
+    }
+}
+
+#[test]
+fn parse_unique() {
+    let id = UniqueThreadId::current();
+    // SAFETY: The text originates from a valid id
+    assert_eq!(unsafe { UniqueThreadId::parse(&id.to_int().to_string()) }, Ok(id));
+    for invalid in ["0", "", "18446744073709551616", "-1", "abc"] {
+        // SAFETY: Invalid text is rejected before creating an id
+        assert!(unsafe { UniqueThreadId::parse(invalid) }.is_err(), "parsed {invalid:?}");
+    }
+}
+
+#[test]
+fn instance_fingerprint() {
+    let fingerprint = threadid::instance_fingerprint();
+    assert_eq!(threadid::instance_fingerprint(), fingerprint);
+    let other_thread = std::thread::spawn(threadid::instance_fingerprint).join().unwrap();
+    assert_eq!(other_thread, fingerprint);
+}
+
+#[test]
+fn to_nonzero_u128() {
+    let unique = UniqueThreadId::current();
+    assert_eq!(unique.to_nonzero_u128().unwrap().get(), u128::from(unique.to_int()));
+    #[cfg(feature = "std")]
+    {
+        let live = LiveThreadId::current();
+        assert_eq!(live.to_nonzero_u128().unwrap().get() - 1, live.to_int() as u128);
+        assert_eq!(
+            LiveThreadId::SENTINEL.to_nonzero_u128().unwrap().get(),
+            usize::MAX as u128
+        );
+        let std = StdThreadId::current();
+        assert_eq!(std.to_nonzero_u128(), std.0.to_nonzero_u128());
+        #[cfg(feature = "nightly")]
+        assert!(std.to_nonzero_u128().is_some());
+    }
+}
+
+#[test]
+fn generic_as_u64() {
+    fn collect<T: IThreadId>() -> HashSet<u64> {
+        let ids = Mutex::new(HashSet::new());
+        thread::scope(|scope| {
+            for _ in 0..4 {
+                scope.spawn(|_scope| {
+                    let id = T::current();
+                    assert_eq!(id.as_u64(), T::current().as_u64());
+                    ids.lock().unwrap().insert(id.as_u64());
+                    barrier_wait();
+                });
+            }
+        })
+        .unwrap();
+        ids.into_inner().unwrap()
+    }
+    // keep every thread alive until all have recorded their id, so live ids are distinct
+    static BARRIER: Barrier = Barrier::new(4);
+    fn barrier_wait() {
+        BARRIER.wait();
+    }
+    assert_eq!(collect::<UniqueThreadId>().len(), 4);
+    #[cfg(feature = "std")]
+    {
+        assert_eq!(collect::<LiveThreadId>().len(), 4);
+        assert_eq!(collect::<StdThreadId>().len(), 4);
+        assert_eq!(collect::<std::thread::ThreadId>().len(), 4);
+        let std = StdThreadId::current();
+        assert_eq!(format!("{:?}", std.0), format!("ThreadId({})", std.as_u64()));
+    }
+}
+
+#[test]
+fn unique_current_checked_in_destructor() {
+    struct ReadOnDrop;
+    impl Drop for ReadOnDrop {
+        fn drop(&mut self) {
+            *DESTRUCTOR_ID.lock().unwrap() = Some(UniqueThreadId::current_checked());
+        }
+    }
+    thread_local! {
+        static READ_ON_DROP: ReadOnDrop = const { ReadOnDrop };
+    }
+    static DESTRUCTOR_ID: Mutex<Option<Option<UniqueThreadId>>> = Mutex::new(None);
+    let id = std::thread::spawn(|| {
+        READ_ON_DROP.with(|_| {});
+        assert_eq!(UniqueThreadId::current_checked(), Some(UniqueThreadId::current()));
+        UniqueThreadId::current()
+    })
+    .join()
+    .unwrap();
+    let destructor_id = DESTRUCTOR_ID.lock().unwrap().expect("destructor did not run");
+    // the id may be unavailable during teardown, but must never be a different id
+    if let Some(destructor_id) = destructor_id {
+        assert_eq!(destructor_id, id);
+    }
+}
+
+#[test]
+#[cfg(all(feature = "nightly", feature = "std"))]
+fn unique_from_std() {
+    let std_id = StdThreadId::current();
+    assert_eq!(UniqueThreadId::from(std_id), UniqueThreadId::current());
+    assert_eq!(UniqueThreadId::from(std_id.0), UniqueThreadId::current());
+    thread::scope(|scope| {
+        let (std_id, unique_id) = scope
+            .spawn(|_scope| (StdThreadId::current(), UniqueThreadId::current()))
+            .join()
+            .unwrap();
+        assert_eq!(UniqueThreadId::from(std_id), unique_id);
+    })
+    .unwrap();
+}
+
+#[test]
+#[cfg(feature = "unique-wrap-std")]
+fn unique_wrap_std() {
+    assert_eq!(
+        UniqueThreadId::from_std(std::thread::current().id()),
+        UniqueThreadId::current()
+    );
+    // converting before the thread requests its own id
+    let barrier = Barrier::new(2);
+    thread::scope(|scope| {
+        let handle = scope.spawn(|_scope| {
+            barrier.wait();
+            UniqueThreadId::current()
+        });
+        let converted = UniqueThreadId::from_std(handle.thread().id());
+        barrier.wait();
+        assert_eq!(handle.join().unwrap(), converted);
+    })
+    .unwrap();
+}
+
+#[test]
+#[cfg(feature = "std")]
+fn std_ordering() {
+    use std::collections::BTreeSet;
+    let ids = (0..4)
+        .map(|_| std::thread::spawn(StdThreadId::current).join().unwrap())
+        .collect::<Vec<_>>();
+    let set = ids.iter().copied().collect::<BTreeSet<_>>();
+    assert_eq!(set.len(), ids.len());
+    for id in &ids {
+        assert!(set.contains(id));
+        assert_eq!(id.cmp(id), std::cmp::Ordering::Equal);
+    }
+    // the ordering is consistent
+    let sorted = set.iter().copied().collect::<Vec<_>>();
+    // the threads were spawned one after another, so their ids are increasing
+    assert_eq!(sorted, ids);
+    for pair in sorted.windows(2) {
+        assert!(pair[0] < pair[1]);
+        assert!(pair[1] > pair[0]);
+    }
+}
+
+#[test]
+fn compact_thread_id() {
+    use threadid::unique::CompactThreadId;
+    assert_eq!(std::mem::size_of::<CompactThreadId>(), 4);
+    assert_eq!(std::mem::size_of::<Option<CompactThreadId>>(), 4);
+    let current = CompactThreadId::current();
+    assert_eq!(CompactThreadId::current(), current);
+    let ids = (0..8)
+        .map(|_| std::thread::spawn(CompactThreadId::current).join().unwrap())
+        .chain(std::iter::once(current))
+        .collect::<HashSet<_>>();
+    assert_eq!(ids.len(), 9);
+}
+
+#[test]
+fn compare_with_int() {
+    let unique = UniqueThreadId::current();
+    let value = unique.to_int();
+    assert!(unique == value);
+    assert!(value == unique);
+    assert!(unique != value + 1);
+    assert!(value + 1 != unique);
+    #[cfg(feature = "std")]
+    {
+        let live = LiveThreadId::current();
+        let index = live.to_int();
+        assert!(live == index);
+        assert!(index == live);
+        assert!(live != index + 1);
+        assert!(index + 1 != live);
+    }
+}
+
+#[test]
+#[cfg(feature = "std")]
+fn current_index() {
+    assert_eq!(threadid::current_index(), LiveThreadId::current().to_int());
+    let (index, explicit) =
+        std::thread::spawn(|| (threadid::current_index(), threadid::current::<LiveThreadId>().to_int()))
+            .join()
+            .unwrap();
+    assert_eq!(index, explicit);
+}
+
+#[test]
+fn atomic_unique_id() {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    use threadid::unique::AtomicUniqueThreadId;
+    let current = UniqueThreadId::current();
+    let atomic = AtomicUniqueThreadId::default();
+    assert_eq!(atomic.load(Ordering::Acquire), None);
+    assert_eq!(atomic.set_current(Ordering::AcqRel), None);
+    assert_eq!(atomic.load(Ordering::Acquire), Some(current));
+    assert_eq!(
+        atomic.compare_exchange(None, None, Ordering::AcqRel, Ordering::Acquire),
+        Err(Some(current))
+    );
+    assert_eq!(
+        atomic.compare_exchange(Some(current), None, Ordering::AcqRel, Ordering::Acquire),
+        Ok(Some(current))
+    );
+    assert_eq!(atomic.swap(Some(current), Ordering::AcqRel), None);
+    atomic.store(None, Ordering::Release);
+    assert_eq!(atomic.into_inner(), None);
+    // threads contend to take ownership, where only the owner may increment the counter
+    const THREADS: usize = 8;
+    const ITERATIONS: usize = 1000;
+    let owner = AtomicUniqueThreadId::new(None);
+    let counter = AtomicUsize::new(0);
+    thread::scope(|scope| {
+        for _ in 0..THREADS {
+            scope.spawn(|_scope| {
+                let id = UniqueThreadId::current();
+                for _ in 0..ITERATIONS {
+                    while owner
+                        .compare_exchange(None, Some(id), Ordering::Acquire, Ordering::Relaxed)
+                        .is_err()
+                    {
+                        std::hint::spin_loop();
+                    }
+                    let value = counter.load(Ordering::Relaxed);
+                    counter.store(value + 1, Ordering::Relaxed);
+                    assert_eq!(owner.swap(None, Ordering::Release), Some(id));
+                }
+            });
+        }
+    })
+    .unwrap();
+    assert_eq!(counter.into_inner(), THREADS * ITERATIONS);
+}
+
+/// A hasher which uses a single integer as the hash, like the `nohash-hasher` crate.
+#[derive(Default)]
+struct IdentityHasher(Option<u64>);
+impl std::hash::Hasher for IdentityHasher {
+    fn finish(&self) -> u64 {
+        self.0.expect("nothing written")
+    }
+
+    fn write(&mut self, _bytes: &[u8]) {
+        panic!("expected a single integer");
+    }
+
+    fn write_u64(&mut self, value: u64) {
+        assert_eq!(self.0.replace(value), None, "expected a single integer");
+    }
+
+    fn write_usize(&mut self, value: usize) {
+        self.write_u64(value as u64);
+    }
+}
+
+#[test]
+fn identity_hash() {
+    use std::collections::HashMap;
+    use std::hash::{BuildHasherDefault, Hash, Hasher};
+    fn hash_one(value: impl Hash) -> u64 {
+        let mut hasher = IdentityHasher::default();
+        value.hash(&mut hasher);
+        hasher.finish()
+    }
+    let id = UniqueThreadId::current();
+    assert_eq!(hash_one(id), id.to_int());
+    assert_eq!(hash_one(id), hash_one(UniqueThreadId::current()));
+    #[cfg(feature = "std")]
+    {
+        let id = LiveThreadId::current();
+        assert_eq!(hash_one(id), id.to_int() as u64);
+        assert_eq!(hash_one(id), hash_one(LiveThreadId::current()));
+    }
+    let ids = (0..4)
+        .map(|_| std::thread::spawn(UniqueThreadId::current).join().unwrap())
+        .collect::<Vec<_>>();
+    let map = ids
+        .iter()
+        .enumerate()
+        .map(|(index, &id)| (id, index))
+        .collect::<HashMap<_, _, BuildHasherDefault<IdentityHasher>>>();
+    assert_eq!(map.len(), ids.len());
+    for (index, id) in ids.iter().enumerate() {
+        assert_eq!(map[id], index);
+    }
+}
+
+#[test]
+fn equivalent_int() {
+    use std::collections::hash_map::DefaultHasher;
+    use std::hash::{Hash, Hasher};
+
+    use equivalent::Equivalent;
+    fn hash(value: impl Hash) -> u64 {
+        let mut hasher = DefaultHasher::new();
+        value.hash(&mut hasher);
+        hasher.finish()
+    }
+    let unique = UniqueThreadId::current();
+    assert!(unique.to_int().equivalent(&unique));
+    assert!(unique.equivalent(&unique.to_int()));
+    assert!(!(unique.to_int() + 1).equivalent(&unique));
+    assert_eq!(hash(unique), hash(unique.to_int()));
+    #[cfg(feature = "std")]
+    {
+        let live = LiveThreadId::current();
+        assert!(live.to_int().equivalent(&live));
+        assert!(live.equivalent(&live.to_int()));
+        assert!(!(live.to_int() + 1).equivalent(&live));
+        assert_eq!(hash(live), hash(live.to_int()));
+    }
+}
+
+#[test]
+fn cached_thread_id() {
+    use threadid::CachedThreadId;
+    fn check<T: IThreadId + Send>() {
+        let cache = CachedThreadId::<T>::default();
+        assert_eq!(cache.get(), T::current());
+        assert_eq!(cache.get(), T::current());
+        let other =
+            thread::scope(|scope| scope.spawn(|_scope| CachedThreadId::<T>::new().get()).join().unwrap()).unwrap();
+        assert_ne!(other, cache.get());
+    }
+    check::<UniqueThreadId>();
+    #[cfg(feature = "std")]
+    {
+        check::<LiveThreadId>();
+        check::<StdThreadId>();
+    }
+}
+
+#[test]
+fn unique_is_current() {
+    let id = UniqueThreadId::current();
+    assert!(id.is_current());
+    let (other, saw_foreign) = thread::scope(|scope| {
+        scope
+            .spawn(|_scope| (UniqueThreadId::current(), id.is_current()))
+            .join()
+            .unwrap()
+    })
+    .unwrap();
+    assert!(!saw_foreign);
+    assert!(!other.is_current());
+}
+
+#[test]
+fn current_raw_niche() {
+    use core::mem::size_of;
+    fn check<T: IThreadId>() {
+        assert_eq!(size_of::<Option<T::Raw>>(), size_of::<T::Raw>());
+        assert_eq!(T::current_raw(), T::current().to_raw());
+    }
+    check::<UniqueThreadId>();
+    check::<threadid::unique::CompactThreadId>();
+    #[cfg(feature = "std")]
+    check::<LiveThreadId>();
+}
+
+#[test]
+#[cfg(feature = "bytemuck")]
+fn checked_bit_pattern() {
+    use bytemuck::checked::{self, CheckedCastError};
+    use threadid::unique::CompactThreadId;
+    let id = UniqueThreadId::current();
+    let valid = id.to_int();
+    assert_eq!(
+        checked::try_from_bytes::<UniqueThreadId>(bytemuck::bytes_of(&valid)),
+        Ok(&id)
+    );
+    assert_eq!(
+        checked::try_from_bytes::<UniqueThreadId>(bytemuck::bytes_of(&0u64)),
+        Err(CheckedCastError::InvalidBitPattern)
+    );
+    let compact = CompactThreadId::current();
+    assert_eq!(checked::try_cast::<u32, CompactThreadId>(compact.to_int()), Ok(compact));
+    assert_eq!(
+        checked::try_cast::<u32, CompactThreadId>(0),
+        Err(CheckedCastError::InvalidBitPattern)
+    );
+}
@@ -0,0 +1,23 @@
+#![cfg(feature = "capi")]
+
+use crossbeam_utils::thread;
+use threadid::capi::{THREADID_ABI_VERSION, threadid_abi_version, threadid_live_current, threadid_unique_current};
+use threadid::{LiveThreadId, UniqueThreadId};
+
+#[test]
+fn matches_rust_api() {
+    thread::scope(|scope| {
+        for _ in 0..4 {
+            scope.spawn(|_scope| {
+                assert_eq!(threadid_unique_current(), UniqueThreadId::current().to_int());
+                assert_eq!(threadid_live_current(), LiveThreadId::current().to_int());
+            });
+        }
+    })
+    .unwrap();
+}
+
+#[test]
+fn abi_version() {
+    assert_eq!(threadid_abi_version(), THREADID_ABI_VERSION);
+}
@@ -0,0 +1,27 @@
+#![cfg(all(feature = "std", feature = "testing"))]
+
+use threadid::live;
+
+/// The number of ids freed at once.
+const CHURN: u64 = 4096;
+
+#[test]
+fn bounded_free_list() {
+    for key in 0..CHURN {
+        let _ = live::current_for_key(key);
+    }
+    for key in 0..CHURN {
+        assert!(live::retire_key(key));
+    }
+    assert!(live::free_list_capacity() >= CHURN as usize);
+    // reusing the ids releases the capacity
+    for key in 0..CHURN {
+        let _ = live::current_for_key(key);
+    }
+    assert!(live::free_list_capacity() <= 128, "{}", live::free_list_capacity());
+    for key in 0..CHURN {
+        assert!(live::retire_key(key));
+    }
+    live::compact();
+    assert_eq!(live::free_list_capacity(), CHURN as usize);
+}
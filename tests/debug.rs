@@ -0,0 +1,311 @@
+use crossbeam_utils::thread;
+use threadid::debug::{self, DebugThreadId};
+
+#[test]
+#[cfg(feature = "std")]
+fn thread_role() {
+    thread::scope(|scope| {
+        scope
+            .builder()
+            .name("worker".into())
+            .spawn(|_scope| {
+                let id = DebugThreadId::current();
+                assert_eq!(id.role(), None);
+                assert_eq!(id.to_string(), format!("{}(\"worker\")", id.id().to_int()));
+                debug::set_current_role("io");
+                let id = DebugThreadId::current();
+                assert_eq!(id.role(), Some("io"));
+                assert_eq!(id.to_string(), format!("{}(\"worker\")[io]", id.id().to_int()));
+                debug::clear_current_role();
+                assert_eq!(DebugThreadId::current().role(), None);
+                debug::set_current_role("io");
+            })
+            .unwrap()
+            .join()
+            .unwrap();
+        // the role is specific to the thread
+        scope
+            .spawn(|_scope| assert_eq!(DebugThreadId::current().role(), None))
+            .join()
+            .unwrap();
+    })
+    .unwrap();
+}
+
+#[test]
+#[cfg(feature = "std")]
+fn current_debug() {
+    thread::scope(|scope| {
+        scope
+            .builder()
+            .name("worker".into())
+            .spawn(|_scope| {
+                let id = threadid::current_debug();
+                assert_eq!(id.id(), threadid::UniqueThreadId::current());
+                assert!(id.to_string().contains("worker"));
+            })
+            .unwrap()
+            .join()
+            .unwrap();
+    })
+    .unwrap();
+}
+
+#[test]
+#[cfg(feature = "std")]
+fn with_current_name() {
+    thread::scope(|scope| {
+        scope
+            .builder()
+            .name("worker".into())
+            .spawn(|_scope| {
+                assert_eq!(
+                    debug::with_current_name(|name| name.map(String::from)),
+                    Some("worker".into())
+                );
+                // cached
+                assert_eq!(
+                    debug::with_current_name(|name| name.map(String::from)),
+                    Some("worker".into())
+                );
+                // reentrant
+                debug::with_current_name(|outer| debug::with_current_name(|inner| assert_eq!(outer, inner)));
+            })
+            .unwrap()
+            .join()
+            .unwrap();
+        scope
+            .spawn(|_scope| {
+                assert_eq!(debug::with_current_name(|name| name.map(String::from)), None);
+                assert_eq!(debug::with_current_name(|name| name.map(String::from)), None);
+            })
+            .join()
+            .unwrap();
+    })
+    .unwrap();
+}
+
+#[test]
+#[cfg(all(feature = "std", feature = "serde"))]
+fn serialize_human_readable() {
+    thread::scope(|scope| {
+        scope
+            .builder()
+            .name("worker".into())
+            .spawn(|_scope| {
+                let id = DebugThreadId::current();
+                let int = id.id().to_int();
+                assert_eq!(
+                    serde_json::to_string(&id).unwrap(),
+                    format!(r#"{{"name":"worker","id":{int}}}"#)
+                );
+            })
+            .unwrap()
+            .join()
+            .unwrap();
+        scope
+            .spawn(|_scope| {
+                let id = DebugThreadId::current();
+                let int = id.id().to_int();
+                assert_eq!(serde_json::to_string(&id).unwrap(), format!(r#"{{"id":{int}}}"#));
+            })
+            .join()
+            .unwrap();
+    })
+    .unwrap();
+}
+
+#[test]
+#[cfg(all(feature = "std", feature = "serde"))]
+fn serialize_compact() {
+    use serde_test::{Configure, Token, assert_ser_tokens};
+    use threadid::debug::DebugLiveThreadId;
+    thread::scope(|scope| {
+        scope
+            .builder()
+            .name("worker".into())
+            .spawn(|_scope| {
+                // compact formats only receive the bare integer id
+                let id = DebugThreadId::current();
+                assert_ser_tokens(&(&id).compact(), &[Token::U64(id.id().to_int())]);
+                let live = DebugLiveThreadId::current();
+                assert_ser_tokens(&(&live).compact(), &[Token::U64(live.id().to_int() as u64)]);
+                // human-readable formats still receive the name
+                assert_ser_tokens(
+                    &(&id).readable(),
+                    &[
+                        Token::Struct {
+                            name: "ThreadDebugId",
+                            len: 2,
+                        },
+                        Token::Str("name"),
+                        Token::Str("worker"),
+                        Token::Str("id"),
+                        Token::U64(id.id().to_int()),
+                        Token::StructEnd,
+                    ],
+                );
+            })
+            .unwrap()
+            .join()
+            .unwrap();
+    })
+    .unwrap();
+}
+
+#[test]
+#[cfg(feature = "std")]
+fn from_parts() {
+    let check = |live: DebugThreadId| {
+        let reconstructed = DebugThreadId::from_parts(live.id(), live.name());
+        assert_eq!(reconstructed.id(), live.id());
+        assert_eq!(reconstructed.name(), live.name());
+        assert_eq!(reconstructed.to_string(), live.to_string());
+        assert_eq!(format!("{reconstructed:?}"), format!("{live:?}"));
+    };
+    thread::scope(|scope| {
+        scope
+            .builder()
+            .name("worker".into())
+            .spawn(|_scope| check(DebugThreadId::current()))
+            .unwrap()
+            .join()
+            .unwrap();
+        scope.spawn(|_scope| check(DebugThreadId::current())).join().unwrap();
+    })
+    .unwrap();
+}
+
+#[test]
+#[cfg(feature = "std")]
+fn debug_live_id_is_alive() {
+    use std::sync::mpsc;
+
+    use threadid::debug::DebugLiveThreadId;
+    let (sender, receiver) = mpsc::channel();
+    let (finish, wait) = mpsc::channel::<()>();
+    let handle = std::thread::spawn(move || {
+        sender.send(DebugLiveThreadId::current()).unwrap();
+        wait.recv().unwrap();
+    });
+    let id = receiver.recv().unwrap();
+    assert!(id.is_alive());
+    finish.send(()).unwrap();
+    // joining waits for the thread to release its id
+    handle.join().unwrap();
+    assert!(!id.is_alive());
+}
+
+#[test]
+#[cfg(feature = "std")]
+fn debug_live_id() {
+    use threadid::LiveThreadId;
+    use threadid::debug::DebugLiveThreadId;
+    thread::scope(|scope| {
+        scope
+            .builder()
+            .name("worker".into())
+            .spawn(|_scope| {
+                let id = DebugLiveThreadId::current();
+                let index = LiveThreadId::current().to_int();
+                assert_eq!(id.id(), LiveThreadId::current());
+                assert_eq!(id.name(), Some("worker"));
+                assert_eq!(id.to_string(), format!("{index}(\"worker\")"));
+                assert_eq!(format!("{id:?}"), format!("LiveThreadId({index}, \"worker\")"));
+            })
+            .unwrap()
+            .join()
+            .unwrap();
+    })
+    .unwrap();
+}
+
+#[test]
+fn registered_name() {
+    thread::scope(|scope| {
+        scope
+            .spawn(|_scope| {
+                let id = DebugThreadId::current();
+                assert_eq!(id.name(), None);
+                assert_eq!(id.to_string(), id.id().to_int().to_string());
+                debug::set_current_name("sensor");
+                let id = DebugThreadId::current();
+                assert_eq!(id.name(), Some("sensor"));
+                assert_eq!(id.to_string(), format!("{}(\"sensor\")", id.id().to_int()));
+                debug::clear_current_name();
+                assert_eq!(DebugThreadId::current().name(), None);
+            })
+            .join()
+            .unwrap();
+        // the registered name overrides the name of a std thread
+        #[cfg(feature = "std")]
+        scope
+            .builder()
+            .name("worker".into())
+            .spawn(|_scope| {
+                assert_eq!(DebugThreadId::current().name(), Some("worker"));
+                debug::set_current_name("sensor");
+                assert_eq!(DebugThreadId::current().name(), Some("sensor"));
+            })
+            .unwrap()
+            .join()
+            .unwrap();
+    })
+    .unwrap();
+}
+
+#[test]
+fn ordering() {
+    use std::collections::BTreeSet;
+    let mut ids = (0..4)
+        .map(|_| std::thread::spawn(DebugThreadId::current).join().unwrap())
+        .collect::<Vec<_>>();
+    ids.reverse();
+    ids.sort();
+    let sorted = ids.iter().map(|id| id.id()).collect::<Vec<_>>();
+    assert!(sorted.windows(2).all(|pair| pair[0] < pair[1]));
+    assert_eq!(ids.iter().cloned().collect::<BTreeSet<_>>().len(), ids.len());
+    // the name and role are ignored
+    let current = DebugThreadId::current();
+    debug::set_current_role("io");
+    assert_eq!(DebugThreadId::current(), current);
+    debug::clear_current_role();
+}
+
+#[test]
+fn display_with() {
+    use threadid::debug::DisplayStyle;
+    thread::scope(|scope| {
+        scope
+            .spawn(|_scope| {
+                let unnamed = DebugThreadId::current();
+                let int = unnamed.id().to_int();
+                for style in [
+                    DisplayStyle::IdOnly,
+                    DisplayStyle::NameOnly,
+                    DisplayStyle::NameThenId,
+                    DisplayStyle::IdThenName,
+                ] {
+                    assert_eq!(unnamed.display_with(style).to_string(), int.to_string());
+                }
+                debug::set_current_name("sensor");
+                debug::set_current_role("io");
+                let named = DebugThreadId::current();
+                assert_eq!(named.display_with(DisplayStyle::IdOnly).to_string(), int.to_string());
+                assert_eq!(named.display_with(DisplayStyle::NameOnly).to_string(), "sensor");
+                assert_eq!(
+                    named.display_with(DisplayStyle::NameThenId).to_string(),
+                    format!("sensor#{int}")
+                );
+                assert_eq!(
+                    named.display_with(DisplayStyle::IdThenName).to_string(),
+                    format!("{int}(\"sensor\")")
+                );
+                // the default layout is unchanged
+                assert_eq!(named.to_string(), format!("{int}(\"sensor\")[io]"));
+            })
+            .join()
+            .unwrap();
+    })
+    .unwrap();
+}
@@ -0,0 +1,19 @@
+#![cfg(not(all(feature = "nightly", feature = "std")))]
+
+use std::num::NonZeroU64;
+
+use crossbeam_utils::thread;
+use threadid::UniqueThreadId;
+use threadid::unique::{self, IdBaseError};
+
+#[test]
+fn set_id_base() {
+    const BASE: u64 = 1 << 32;
+    assert_eq!(unique::set_id_base(NonZeroU64::new(BASE).unwrap()), Ok(()));
+    let first = UniqueThreadId::current();
+    assert!(first.to_int() >= BASE);
+    let second = thread::scope(|scope| scope.spawn(|_scope| UniqueThreadId::current()).join().unwrap()).unwrap();
+    assert!(second.to_int() > first.to_int());
+    // the base can no longer be changed
+    assert_eq!(unique::set_id_base(NonZeroU64::new(1).unwrap()), Err(IdBaseError));
+}
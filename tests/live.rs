@@ -0,0 +1,604 @@
+#![cfg(feature = "std")]
+
+use std::sync::{Barrier, Mutex, PoisonError};
+
+use crossbeam_utils::thread;
+use threadid::LiveThreadId;
+use threadid::live::{self, ThreadPartitioner};
+
+/// Serializes the tests which depend on the global state of the allocator.
+static ALLOCATOR_LOCK: Mutex<()> = Mutex::new(());
+
+/// Run the function on a fresh thread,
+/// without any other test from this file running concurrently.
+///
+/// The threads of the test harness never request a [`LiveThreadId`],
+/// so the only ids in use are the ones requested by the function.
+fn isolated<R: Send>(func: impl FnOnce() -> R + Send) -> R {
+    let _lock = ALLOCATOR_LOCK.lock().unwrap_or_else(PoisonError::into_inner);
+    thread::scope(|scope| {
+        scope
+            .spawn(|_scope| func())
+            .join()
+            .unwrap_or_else(|payload| std::panic::resume_unwind(payload))
+    })
+    .unwrap()
+}
+
+#[test]
+fn sentinel() {
+    isolated(|| {
+        assert!(LiveThreadId::SENTINEL.is_sentinel());
+        assert_eq!(LiveThreadId::default(), LiveThreadId::SENTINEL);
+        assert_eq!(LiveThreadId::SENTINEL.to_int(), usize::MAX - 1);
+        assert!(!LiveThreadId::current().is_sentinel());
+    });
+}
+
+#[test]
+fn partition_by_thread() {
+    const THREADS: usize = 4;
+    const ITEMS: usize = 100;
+    let partitioner = ThreadPartitioner::new();
+    let barrier = Barrier::new(THREADS);
+    isolated(|| {
+        thread::scope(|scope| {
+            for _ in 0..THREADS {
+                scope.spawn(|_scope| {
+                    // keep all threads alive, so that ids are distinct
+                    barrier.wait();
+                    for _ in 0..ITEMS {
+                        partitioner.push(LiveThreadId::current());
+                    }
+                    barrier.wait();
+                });
+            }
+        })
+        .unwrap();
+    });
+    let buckets = partitioner.into_buckets();
+    assert_eq!(buckets.len(), THREADS);
+    for (id, bucket) in buckets {
+        assert_eq!(bucket.len(), ITEMS);
+        assert!(bucket.iter().all(|&item| item == id));
+    }
+}
+
+#[test]
+fn single_threaded() {
+    isolated(|| {
+        assert!(!live::is_single_threaded());
+        let _ = LiveThreadId::current();
+        assert!(live::is_single_threaded());
+        let barrier = Barrier::new(2);
+        thread::scope(|scope| {
+            scope.spawn(|_scope| {
+                let _ = LiveThreadId::current();
+                barrier.wait();
+                barrier.wait();
+            });
+            barrier.wait();
+            assert!(!live::is_single_threaded());
+            barrier.wait();
+        })
+        .unwrap();
+        assert!(live::is_single_threaded());
+    });
+}
+
+#[test]
+fn predict_next() {
+    isolated(|| {
+        let first = live::predict_next_id();
+        assert_eq!(LiveThreadId::current(), first);
+        thread::scope(|scope| {
+            let predicted = live::predict_next_id();
+            assert_ne!(predicted, first);
+            let second = scope.spawn(|_scope| LiveThreadId::current()).join().unwrap();
+            assert_eq!(second, predicted);
+            // the above thread has died, so its id will be reused
+            assert_eq!(live::predict_next_id(), second);
+            let third = scope.spawn(|_scope| LiveThreadId::current()).join().unwrap();
+            assert_eq!(third, second);
+        })
+        .unwrap();
+    });
+}
+
+#[test]
+fn try_current_in_destructor() {
+    use std::cell::Cell;
+
+    use threadid::{AccessError, IThreadId};
+
+    static RESULT: Mutex<Option<Result<LiveThreadId, AccessError>>> = Mutex::new(None);
+    struct QueryOnDrop(Cell<bool>);
+    impl Drop for QueryOnDrop {
+        fn drop(&mut self) {
+            *RESULT.lock().unwrap() = Some(<LiveThreadId as IThreadId>::try_current());
+        }
+    }
+    std::thread_local! {
+        static QUERY: QueryOnDrop = const { QueryOnDrop(Cell::new(false)) };
+    }
+    isolated(|| {
+        thread::scope(|scope| {
+            scope
+                .spawn(|_scope| {
+                    // registered before the id, so its destructor runs afterwards
+                    QUERY.with(|query| query.0.set(true));
+                    assert_eq!(LiveThreadId::try_current(), Ok(LiveThreadId::current()));
+                })
+                .join()
+                .unwrap();
+        })
+        .unwrap();
+    });
+    assert_eq!(*RESULT.lock().unwrap(), Some(Err(AccessError::Destroyed)));
+}
+
+#[test]
+fn live_count() {
+    const THREADS: usize = 8;
+    isolated(|| {
+        assert_eq!(LiveThreadId::live_count(), 0);
+        let barrier = Barrier::new(THREADS + 1);
+        thread::scope(|scope| {
+            for _ in 0..THREADS {
+                scope.spawn(|_scope| {
+                    let _ = LiveThreadId::current();
+                    barrier.wait();
+                    barrier.wait();
+                });
+            }
+            barrier.wait();
+            assert_eq!(LiveThreadId::live_count(), THREADS);
+            barrier.wait();
+        })
+        .unwrap();
+        assert_eq!(LiveThreadId::live_count(), 0);
+    });
+}
+
+#[test]
+fn max_allocated() {
+    const THREADS: usize = 4;
+    isolated(|| {
+        let initial = LiveThreadId::max_allocated();
+        let barrier = Barrier::new(THREADS + 1);
+        thread::scope(|scope| {
+            for _ in 0..THREADS {
+                scope.spawn(|_scope| {
+                    let _ = LiveThreadId::current();
+                    barrier.wait();
+                    barrier.wait();
+                });
+            }
+            barrier.wait();
+            let max = LiveThreadId::max_allocated().unwrap();
+            assert!(max >= THREADS - 1);
+            assert!(initial.map_or(true, |initial| max >= initial));
+            barrier.wait();
+        })
+        .unwrap();
+        let high_water = LiveThreadId::max_allocated();
+        assert!(high_water.is_some());
+        // dead threads do not lower the high-water mark, and their ids are reused
+        let id = LiveThreadId::current();
+        assert!(id.to_int() <= high_water.unwrap());
+        assert_eq!(LiveThreadId::max_allocated(), high_water);
+    });
+}
+
+#[test]
+fn external_keys() {
+    isolated(|| {
+        let first = live::current_for_key(1);
+        assert_eq!(live::current_for_key(1), first);
+        // the id belongs to the key, not the thread
+        let other_thread =
+            thread::scope(|scope| scope.spawn(|_scope| live::current_for_key(1)).join().unwrap()).unwrap();
+        assert_eq!(other_thread, first);
+        let second = live::current_for_key(2);
+        assert_ne!(second, first);
+        assert_ne!(LiveThreadId::current(), first);
+        // retiring a key allows its id to be reused
+        assert!(live::retire_key(1));
+        assert!(!live::retire_key(1));
+        assert_eq!(live::current_for_key(3), first);
+        assert!(live::retire_key(2));
+        assert!(live::retire_key(3));
+    });
+}
+
+#[test]
+fn iter_live() {
+    const THREADS: usize = 4;
+    isolated(|| {
+        assert_eq!(live::iter_live().count(), 0);
+        let current = LiveThreadId::current();
+        assert_eq!(live::iter_live().collect::<Vec<_>>(), vec![current]);
+        let barrier = Barrier::new(THREADS + 1);
+        let (ids, snapshot) = thread::scope(|scope| {
+            let handles = (0..THREADS)
+                .map(|_| {
+                    scope.spawn(|_scope| {
+                        let id = LiveThreadId::current();
+                        barrier.wait();
+                        barrier.wait();
+                        id
+                    })
+                })
+                .collect::<Vec<_>>();
+            barrier.wait();
+            // start iterating while the threads are alive, then let them die
+            let mut iter = live::iter_live();
+            let first = iter.next().unwrap();
+            barrier.wait();
+            let ids = handles
+                .into_iter()
+                .map(|handle| handle.join().unwrap())
+                .collect::<Vec<_>>();
+            let snapshot = std::iter::once(first).chain(iter).collect::<Vec<_>>();
+            (ids, snapshot)
+        })
+        .unwrap();
+        let mut expected = ids;
+        expected.push(current);
+        expected.sort();
+        assert_eq!(snapshot, expected);
+        assert_eq!(live::iter_live().collect::<Vec<_>>(), vec![current]);
+    });
+}
+
+#[test]
+fn thread_indexed_vec() {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    use threadid::live::ThreadIndexedVec;
+    const THREADS: usize = 4;
+    const ROUNDS: usize = 8;
+    isolated(|| {
+        let mut vec = ThreadIndexedVec::<AtomicUsize>::new();
+        for _ in 0..ROUNDS {
+            thread::scope(|scope| {
+                for _ in 0..THREADS {
+                    scope.spawn(|_scope| {
+                        let slot = vec.get_or_insert_with(|| AtomicUsize::new(0));
+                        assert!(std::ptr::eq(slot, vec.get_or_insert_with(|| unreachable!())));
+                        slot.fetch_add(1, Ordering::Relaxed);
+                    });
+                }
+            })
+            .unwrap();
+        }
+        // dead threads leave their slots to the threads which reuse their ids
+        let total = vec.iter().map(|(_, slot)| slot.load(Ordering::Relaxed)).sum::<usize>();
+        assert_eq!(total, THREADS * ROUNDS);
+        assert!(vec.iter().count() <= THREADS);
+        let ids = vec.iter_mut().map(|(id, _)| id).collect::<Vec<_>>();
+        let mut sorted = ids.clone();
+        sorted.sort();
+        assert_eq!(ids, sorted);
+    });
+}
+
+#[test]
+fn on_thread_exit() {
+    use std::sync::Arc;
+    isolated(|| {
+        let events = Arc::new(Mutex::new(Vec::new()));
+        let id = thread::scope(|scope| {
+            scope
+                .spawn(|_scope| {
+                    for order in 0..2 {
+                        let events = Arc::clone(&events);
+                        assert!(live::on_thread_exit(move |id| {
+                            // the id has not been released yet
+                            let in_use = live::iter_live().any(|live| live == id);
+                            events.lock().unwrap().push((order, id, in_use));
+                        }));
+                    }
+                    assert!(events.lock().unwrap().is_empty());
+                    LiveThreadId::current()
+                })
+                .join()
+                .unwrap()
+        })
+        .unwrap();
+        assert_eq!(*events.lock().unwrap(), vec![(1, id, true), (0, id, true)]);
+        assert_eq!(LiveThreadId::live_count(), 0);
+    });
+}
+
+#[test]
+fn on_thread_exit_nested() {
+    use std::sync::Arc;
+    use std::sync::atomic::{AtomicBool, Ordering};
+    isolated(|| {
+        let rejected = Arc::new(AtomicBool::new(false));
+        let nested_ran = Arc::new(AtomicBool::new(false));
+        thread::scope(|scope| {
+            scope
+                .spawn(|_scope| {
+                    let rejected = Arc::clone(&rejected);
+                    let nested_ran = Arc::clone(&nested_ran);
+                    assert!(live::on_thread_exit(move |_id| {
+                        let registered = live::on_thread_exit(move |_id| nested_ran.store(true, Ordering::SeqCst));
+                        rejected.store(!registered, Ordering::SeqCst);
+                    }));
+                })
+                .join()
+                .unwrap();
+        })
+        .unwrap();
+        // registering from a callback is rejected instead of aborting
+        assert!(rejected.load(Ordering::SeqCst));
+        assert!(!nested_ran.load(Ordering::SeqCst));
+        assert_eq!(LiveThreadId::live_count(), 0);
+    });
+}
+
+#[test]
+fn versioned() {
+    use threadid::live::VersionedLiveThreadId;
+    isolated(|| {
+        let spawn_versioned = || {
+            thread::scope(|scope| {
+                scope
+                    .spawn(|_scope| {
+                        let id = VersionedLiveThreadId::current();
+                        assert_eq!(id, VersionedLiveThreadId::current());
+                        assert_eq!(id.id(), LiveThreadId::current());
+                        assert!(!id.is_stale());
+                        id
+                    })
+                    .join()
+                    .unwrap()
+            })
+            .unwrap()
+        };
+        let first = spawn_versioned();
+        assert!(first.is_stale());
+        let second = spawn_versioned();
+        // the index is reused, but the generation distinguishes the threads
+        assert_eq!(second.index(), first.index());
+        assert!(second.generation() > first.generation());
+        assert_ne!(second, first);
+        assert!(second.is_stale());
+    });
+}
+
+#[test]
+fn with_current() {
+    isolated(|| {
+        let current = LiveThreadId::current();
+        assert_eq!(LiveThreadId::with_current(|id| id), current);
+        assert_eq!(LiveThreadId::with_current(LiveThreadId::to_int), current.to_int());
+    });
+}
+
+#[test]
+#[cfg(feature = "rayon")]
+fn rayon_worker_index() {
+    isolated(|| {
+        use std::collections::HashSet;
+        const THREADS: usize = 4;
+        assert_eq!(live::rayon_worker_index(), None);
+        // join the workers afterwards, so their ids are released before the next test
+        let mut workers = Vec::new();
+        let pool = rayon::ThreadPoolBuilder::new()
+            .num_threads(THREADS)
+            .spawn_handler(|worker| {
+                workers.push(std::thread::spawn(|| worker.run()));
+                Ok(())
+            })
+            .build()
+            .unwrap();
+        let seen = pool.broadcast(|context| {
+            let index = live::rayon_worker_index().unwrap();
+            assert_eq!(index, context.index());
+            (index, LiveThreadId::current())
+        });
+        drop(pool);
+        for worker in workers {
+            worker.join().unwrap();
+        }
+        let indices = seen.iter().map(|&(index, _)| index).collect::<HashSet<_>>();
+        assert_eq!(indices, (0..THREADS).collect());
+        let live_ids = seen.iter().map(|&(_, id)| id).collect::<HashSet<_>>();
+        assert_eq!(live_ids.len(), THREADS);
+    });
+}
+
+#[test]
+fn current_or_none() {
+    isolated(|| {
+        let before = live::predict_next_id();
+        assert_eq!(LiveThreadId::current_or_none(), None);
+        // no id was allocated
+        assert_eq!(live::predict_next_id(), before);
+        let id = LiveThreadId::current();
+        assert_eq!(LiveThreadId::current_or_none(), Some(id));
+    });
+}
+
+#[test]
+fn live_is_current() {
+    isolated(|| {
+        let id = LiveThreadId::current();
+        assert!(id.is_current());
+        let saw_foreign = thread::scope(|scope| {
+            scope
+                .spawn(|_scope| {
+                    let before = live::predict_next_id();
+                    let result = id.is_current();
+                    // checking did not allocate an id
+                    assert_eq!(live::predict_next_id(), before);
+                    assert_eq!(LiveThreadId::current_or_none(), None);
+                    result
+                })
+                .join()
+                .unwrap()
+        })
+        .unwrap();
+        assert!(!saw_foreign);
+    });
+}
+
+#[test]
+#[cfg(feature = "execution-id")]
+fn execution_id() {
+    use threadid::live::ExecutionId;
+    isolated(|| {
+        let thread = LiveThreadId::current();
+        let first = ExecutionId::current(1);
+        assert_eq!(first, ExecutionId::new(thread, 1));
+        assert_eq!(first.thread(), thread);
+        assert_eq!(first.task(), 1);
+        assert_eq!(first.to_string(), format!("{}.1", thread.to_int()));
+        let second = ExecutionId::current(2);
+        assert_ne!(first, second);
+        assert!(first < second);
+        let other = thread::scope(|scope| scope.spawn(|_scope| ExecutionId::current(1)).join().unwrap()).unwrap();
+        assert_ne!(other, first);
+        assert_eq!(other.task(), first.task());
+        #[cfg(feature = "serde")]
+        assert_eq!(
+            serde_json::to_string(&first).unwrap(),
+            format!(r#"{{"thread":{},"task":1}}"#, thread.to_int())
+        );
+    });
+}
+
+#[test]
+fn thread_id_set() {
+    use threadid::live::ThreadIdSet;
+    isolated(|| {
+        let mut set = ThreadIdSet::new();
+        assert!(set.is_empty());
+        assert!(set.insert_current());
+        assert!(!set.insert_current());
+        let current = LiveThreadId::current();
+        assert!(set.contains(current));
+        // ids spanning multiple words
+        let barrier = Barrier::new(100);
+        let others = thread::scope(|scope| {
+            let handles = (0..100)
+                .map(|_| {
+                    scope.spawn(|_scope| {
+                        let id = LiveThreadId::current();
+                        barrier.wait();
+                        id
+                    })
+                })
+                .collect::<Vec<_>>();
+            handles
+                .into_iter()
+                .map(|handle| handle.join().unwrap())
+                .collect::<Vec<_>>()
+        })
+        .unwrap();
+        let largest = *others.iter().max().unwrap();
+        assert!(!set.contains(largest));
+        assert!(set.insert(largest));
+        assert_eq!(set.len(), 2);
+        assert_eq!(set.iter().collect::<Vec<_>>(), vec![current, largest]);
+        assert!(set.remove(largest));
+        assert!(!set.remove(largest));
+        assert_eq!(set.iter().collect::<Vec<_>>(), vec![current]);
+        let all = others.iter().copied().collect::<ThreadIdSet>();
+        assert_eq!(all.len(), others.len());
+        let mut sorted = others.clone();
+        sorted.sort();
+        assert_eq!(all.iter().collect::<Vec<_>>(), sorted);
+        // the sentinel is rejected instead of growing the set
+        assert!(!set.insert(LiveThreadId::SENTINEL));
+        assert!(!set.insert(LiveThreadId::default()));
+        assert!(!set.contains(LiveThreadId::SENTINEL));
+        assert_eq!(set.len(), 1);
+        set.clear();
+        assert!(set.is_empty());
+        assert!(!set.contains(current));
+    });
+}
+
+#[test]
+fn int_opt_round_trip() {
+    isolated(|| {
+        let id = LiveThreadId::current();
+        assert_eq!(LiveThreadId::to_int_opt(Some(id)), id.to_int());
+        assert_eq!(LiveThreadId::to_int_opt(None), usize::MAX);
+        // SAFETY: The integers originate from the id of the current thread, the sentinel, or represent `None`
+        unsafe {
+            assert_eq!(LiveThreadId::from_int_opt(LiveThreadId::to_int_opt(Some(id))), Some(id));
+            assert_eq!(LiveThreadId::from_int_opt(LiveThreadId::to_int_opt(None)), None);
+            // the sentinel is a valid id, and is distinct from `None`
+            let sentinel = LiveThreadId::to_int_opt(Some(LiveThreadId::SENTINEL));
+            assert_ne!(sentinel, usize::MAX);
+            assert_eq!(LiveThreadId::from_int_opt(sentinel), Some(LiveThreadId::SENTINEL));
+        }
+    });
+}
+
+#[test]
+fn tagged_index() {
+    use threadid::live::{TaggedIndex, VersionedLiveThreadId};
+    assert_eq!(TaggedIndex::INDEX_BITS + TaggedIndex::EPOCH_BITS, usize::BITS);
+    #[cfg(target_pointer_width = "64")]
+    assert_eq!((TaggedIndex::INDEX_BITS, TaggedIndex::EPOCH_BITS), (48, 16));
+    isolated(|| {
+        let spawn_tagged = || {
+            thread::scope(|scope| {
+                scope
+                    .spawn(|_scope| {
+                        let tag = live::current_tagged();
+                        let versioned = VersionedLiveThreadId::current();
+                        assert_eq!(TaggedIndex::from_versioned(versioned), Some(tag));
+                        assert_eq!(tag.id(), LiveThreadId::current());
+                        assert_eq!(tag.index(), versioned.index());
+                        assert!(tag.matches_current());
+                        // SAFETY: The bits were returned by `to_bits`
+                        assert_eq!(unsafe { TaggedIndex::from_bits(tag.to_bits()) }, tag);
+                        tag
+                    })
+                    .join()
+                    .unwrap()
+            })
+            .unwrap()
+        };
+        let first = spawn_tagged();
+        let second = spawn_tagged();
+        // the index is reused, but the epoch detects the recycling
+        assert_eq!(second.index(), first.index());
+        assert_ne!(second.epoch(), first.epoch());
+        assert_ne!(second, first);
+        thread::scope(|scope| {
+            scope
+                .spawn(|_scope| {
+                    assert_eq!(LiveThreadId::current(), first.id());
+                    assert!(!first.matches_current());
+                    assert!(!second.matches_current());
+                })
+                .join()
+                .unwrap();
+        })
+        .unwrap();
+    });
+}
+
+#[test]
+fn tagged_index_epoch_wraps() {
+    isolated(|| {
+        let spawn_tagged =
+            || thread::scope(|scope| scope.spawn(|_scope| live::current_tagged()).join().unwrap()).unwrap();
+        let first = spawn_tagged();
+        // the dead thread released the index once, so recycle it until the epoch wraps around
+        for key in 1..1u64 << threadid::live::TaggedIndex::EPOCH_BITS {
+            assert_eq!(live::current_for_key(key), first.id());
+            assert!(live::retire_key(key));
+        }
+        let wrapped = spawn_tagged();
+        assert_eq!(wrapped, first);
+    });
+}
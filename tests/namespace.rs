@@ -0,0 +1,101 @@
+#![cfg(feature = "std")]
+
+use std::marker::PhantomData;
+use std::sync::Barrier;
+
+use crossbeam_utils::thread;
+use threadid::namespace::{NamespacedLiveId, NamespacedUniqueId, ThreadIdNamespace};
+use threadid::{IThreadId, LiveThreadId, UniqueThreadId};
+
+/// Detects whether a concrete type implements [`IThreadId`],
+/// because the inherent method takes precedence over the trait method when it applies.
+struct Probe<T>(PhantomData<T>);
+impl<T: IThreadId> Probe<T> {
+    fn is_thread_id(&self) -> bool {
+        true
+    }
+}
+trait NotThreadId {
+    fn is_thread_id(&self) -> bool {
+        false
+    }
+}
+impl<T> NotThreadId for Probe<T> {}
+macro_rules! is_thread_id {
+    ($target:ty) => {
+        Probe::<$target>(PhantomData).is_thread_id()
+    };
+}
+
+#[test]
+fn independent_namespaces() {
+    let first = ThreadIdNamespace::new();
+    let second = ThreadIdNamespace::new();
+    assert_eq!(first.unique_current().to_int(), 1);
+    assert_eq!(first.live_current().to_int(), 0);
+    // cached
+    assert_eq!(first.unique_current().to_int(), 1);
+    assert_eq!(first.live_current().to_int(), 0);
+    thread::scope(|scope| {
+        scope
+            .spawn(|_scope| {
+                assert_eq!(first.unique_current().to_int(), 2);
+                assert_eq!(first.live_current().to_int(), 1);
+                assert_eq!(second.unique_current().to_int(), 1);
+                assert_eq!(second.live_current().to_int(), 0);
+            })
+            .join()
+            .unwrap();
+    })
+    .unwrap();
+}
+
+#[test]
+fn namespace_death_reuse() {
+    let namespace = ThreadIdNamespace::new();
+    let ids = thread::scope(|scope| {
+        let first = scope
+            .spawn(|_scope| (namespace.unique_current(), namespace.live_current()))
+            .join()
+            .unwrap();
+        // above thread has died
+        let second = scope
+            .spawn(|_scope| (namespace.unique_current(), namespace.live_current()))
+            .join()
+            .unwrap();
+        (first, second)
+    })
+    .unwrap();
+    let ((first_unique, first_live), (second_unique, second_live)) = ids;
+    assert_ne!(first_unique, second_unique);
+    assert_eq!(first_live, second_live);
+}
+
+#[test]
+fn namespaced_ids_are_distinct() {
+    let namespace = ThreadIdNamespace::new();
+    let barrier = Barrier::new(2);
+    let (global, namespaced) = thread::scope(|scope| {
+        let global = scope.spawn(|_scope| {
+            let id = LiveThreadId::current();
+            barrier.wait();
+            id
+        });
+        let namespaced = scope.spawn(|_scope| {
+            let id = namespace.live_current();
+            barrier.wait();
+            id
+        });
+        (global.join().unwrap(), namespaced.join().unwrap())
+    })
+    .unwrap();
+    // no other test in this file uses the global allocator,
+    // so both threads received the first id of their allocator
+    assert_eq!(global.to_int(), 0);
+    assert_eq!(namespaced.to_int(), global.to_int());
+    // despite the equal integers, the namespaced id is not a thread id
+    assert!(is_thread_id!(LiveThreadId));
+    assert!(is_thread_id!(UniqueThreadId));
+    assert!(!is_thread_id!(NamespacedLiveId));
+    assert!(!is_thread_id!(NamespacedUniqueId));
+}
@@ -0,0 +1,75 @@
+#![cfg(feature = "testing")]
+
+use crossbeam_utils::thread;
+use threadid::unique::{self, CompactThreadId, ThreadIdOverflow};
+use threadid::{AccessError, IThreadId};
+
+#[test]
+#[cfg(all(feature = "std", not(feature = "nightly")))]
+fn overflow() {
+    use threadid::UniqueThreadId;
+    let existing = UniqueThreadId::current();
+    // SAFETY: Only increases the counter
+    unsafe {
+        unique::set_next_id_for_testing(1 << 63);
+    }
+    thread::scope(|scope| {
+        scope
+            .spawn(|_scope| {
+                assert_eq!(UniqueThreadId::try_current(), Err(ThreadIdOverflow));
+                assert_eq!(<UniqueThreadId as IThreadId>::try_current(), Err(AccessError::Overflow));
+                assert_eq!(UniqueThreadId::current_checked(), None);
+            })
+            .join()
+            .unwrap();
+        let panic = scope.spawn(|_scope| UniqueThreadId::current()).join().unwrap_err();
+        assert_eq!(panic.downcast_ref::<String>().map(String::as_str), Some("id overflow"));
+    })
+    .unwrap();
+    // threads which already have an id are unaffected
+    assert_eq!(UniqueThreadId::try_current(), Ok(existing));
+    // wrapping the counter around to zero reports overflow instead of panicking
+    // SAFETY: Only increases the counter, and no ids are allocated after it wraps
+    unsafe {
+        unique::set_next_id_for_testing(u64::MAX);
+    }
+    thread::scope(|scope| {
+        for _ in 0..2 {
+            let result = scope.spawn(|_scope| UniqueThreadId::try_current()).join().unwrap();
+            assert_eq!(result, Err(ThreadIdOverflow));
+        }
+    })
+    .unwrap();
+}
+
+#[test]
+fn compact_overflow() {
+    let existing = CompactThreadId::current();
+    // SAFETY: Only increases the counter
+    unsafe {
+        unique::set_next_compact_id_for_testing(u32::MAX - 1);
+    }
+    thread::scope(|scope| {
+        // the last id is still allocated
+        let last = scope.spawn(|_scope| CompactThreadId::try_current()).join().unwrap();
+        assert_eq!(last.map(|id| id.to_int()), Ok(u32::MAX - 1));
+        scope
+            .spawn(|_scope| {
+                assert_eq!(CompactThreadId::try_current(), Err(ThreadIdOverflow));
+                assert_eq!(
+                    <CompactThreadId as IThreadId>::try_current(),
+                    Err(AccessError::Overflow)
+                );
+            })
+            .join()
+            .unwrap();
+        let panic = scope.spawn(|_scope| CompactThreadId::current()).join().unwrap_err();
+        assert_eq!(
+            panic.downcast_ref::<&str>().copied(),
+            Some("CompactThreadId overflowed a u32")
+        );
+    })
+    .unwrap();
+    // threads which already have an id are unaffected
+    assert_eq!(CompactThreadId::try_current(), Ok(existing));
+}
@@ -0,0 +1,189 @@
+//! Checks that recursively allocating an id is detected in debug builds.
+#![cfg(all(feature = "std", debug_assertions))]
+
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::cell::Cell;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::{Mutex, MutexGuard, PoisonError};
+
+use crossbeam_utils::thread;
+use threadid::LiveThreadId;
+#[cfg(feature = "testing")]
+use threadid::UniqueThreadId;
+#[cfg(feature = "testing")]
+use threadid::unique::CompactThreadId;
+
+/// A global allocator which runs a per-thread hook when allocating.
+struct ReentrantAllocator;
+
+/// The outcome of running the hooks of a single test.
+struct Outcome {
+    /// The number of times the hook ran.
+    lookups: AtomicUsize,
+    /// Whether the hook panicked because of recursion.
+    detected: AtomicBool,
+}
+impl Outcome {
+    const fn new() -> Outcome {
+        Outcome {
+            lookups: AtomicUsize::new(0),
+            detected: AtomicBool::new(false),
+        }
+    }
+}
+
+/// A function to run when allocating, and where to record its outcome.
+type Hook = (fn(), &'static Outcome);
+
+thread_local! {
+    static HOOK: Cell<Option<Hook>> = const { Cell::new(None) };
+    static IN_HOOK: Cell<bool> = const { Cell::new(false) };
+}
+
+fn set_hook(lookup: fn(), outcome: &'static Outcome) {
+    HOOK.with(|hook| hook.set(Some((lookup, outcome))));
+}
+
+#[cfg(feature = "testing")]
+fn clear_hook() {
+    HOOK.with(|hook| hook.set(None));
+}
+
+impl ReentrantAllocator {
+    fn run_hook() {
+        let (lookup, outcome) = match HOOK.try_with(Cell::get) {
+            Ok(Some(hook)) => hook,
+            _ => return,
+        };
+        if IN_HOOK.with(|flag| flag.replace(true)) {
+            return;
+        }
+        outcome.lookups.fetch_add(1, Ordering::SeqCst);
+        if let Err(payload) = std::panic::catch_unwind(lookup) {
+            let message = payload
+                .downcast_ref::<&'static str>()
+                .copied()
+                .or_else(|| payload.downcast_ref::<String>().map(String::as_str));
+            if message.map_or(false, |message| message.contains("recursively")) {
+                outcome.detected.store(true, Ordering::SeqCst);
+            }
+        }
+        IN_HOOK.with(|flag| flag.set(false));
+    }
+}
+
+// SAFETY: Delegates to the system allocator
+unsafe impl GlobalAlloc for ReentrantAllocator {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        Self::run_hook();
+        // SAFETY: Guaranteed by the caller
+        unsafe { System.alloc(layout) }
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        // SAFETY: Guaranteed by the caller
+        unsafe { System.dealloc(ptr, layout) }
+    }
+}
+
+#[global_allocator]
+static ALLOCATOR: ReentrantAllocator = ReentrantAllocator;
+
+/// Prevents the tests from changing the state of the shared allocator concurrently.
+fn serialize() -> MutexGuard<'static, ()> {
+    static LOCK: Mutex<()> = Mutex::new(());
+    LOCK.lock().unwrap_or_else(PoisonError::into_inner)
+}
+
+/// Run the hook on a fresh thread while it allocates its [`LiveThreadId`].
+#[cfg(feature = "testing")]
+fn run_during_alloc(lookup: fn(), outcome: &'static Outcome) {
+    thread::scope(|scope| {
+        scope
+            .spawn(|_scope| {
+                set_hook(lookup, outcome);
+                let _ = LiveThreadId::current();
+                clear_hook();
+            })
+            .join()
+            .unwrap();
+    })
+    .unwrap();
+}
+
+/// Fill the free list, then drain it until the next allocation shrinks it.
+///
+/// Allocating an id never requires memory otherwise.
+#[cfg(feature = "testing")]
+fn prepare_shrinking_alloc() {
+    const COUNT: u64 = 256;
+    // SAFETY: The tests are serialized, and no other threads hold ids
+    unsafe {
+        threadid::live::reset_allocator();
+    }
+    for key in 0..COUNT {
+        let _ = threadid::live::current_for_key(key);
+    }
+    for key in 0..COUNT {
+        threadid::live::retire_key(key);
+    }
+    let capacity = threadid::live::free_list_capacity();
+    let mut len = COUNT as usize;
+    let mut key = COUNT;
+    while len > capacity / 4 {
+        let _ = threadid::live::current_for_key(key);
+        key += 1;
+        len -= 1;
+    }
+}
+
+fn live_lookup() {
+    let _ = LiveThreadId::current();
+}
+
+#[test]
+fn detect_recursive_free() {
+    static OUTCOME: Outcome = Outcome::new();
+    let _lock = serialize();
+    thread::scope(|scope| {
+        scope
+            .spawn(|_scope| {
+                let _ = LiveThreadId::current();
+                // leave no spare capacity, so freeing the id allocates space in the free list,
+                // which looks up the id again
+                threadid::live::compact();
+                set_hook(live_lookup, &OUTCOME);
+            })
+            .join()
+            .unwrap();
+    })
+    .unwrap();
+    assert!(OUTCOME.detected.load(Ordering::SeqCst));
+}
+
+#[test]
+#[cfg(feature = "testing")]
+fn detect_recursive_alloc() {
+    static OUTCOME: Outcome = Outcome::new();
+    let _lock = serialize();
+    prepare_shrinking_alloc();
+    run_during_alloc(live_lookup, &OUTCOME);
+    assert!(OUTCOME.lookups.load(Ordering::SeqCst) > 0);
+    assert!(OUTCOME.detected.load(Ordering::SeqCst));
+}
+
+/// Allocating other kinds of ids while allocating a [`LiveThreadId`] is not recursion.
+#[test]
+#[cfg(feature = "testing")]
+fn allow_other_ids_during_alloc() {
+    static OUTCOME: Outcome = Outcome::new();
+    fn other_lookup() {
+        let _ = UniqueThreadId::current();
+        let _ = CompactThreadId::current();
+    }
+    let _lock = serialize();
+    prepare_shrinking_alloc();
+    run_during_alloc(other_lookup, &OUTCOME);
+    assert!(OUTCOME.lookups.load(Ordering::SeqCst) > 0);
+    assert!(!OUTCOME.detected.load(Ordering::SeqCst));
+}
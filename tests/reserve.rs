@@ -0,0 +1,32 @@
+#![cfg(all(feature = "std", feature = "testing"))]
+
+use std::sync::Barrier;
+
+use crossbeam_utils::thread;
+use threadid::{LiveThreadId, live};
+
+/// The number of threads spawned simultaneously.
+const THREADS: usize = 64;
+
+#[test]
+fn reserve() {
+    live::reserve(1024);
+    let capacity = live::free_list_capacity();
+    assert!(capacity >= 1024);
+    for _ in 0..4 {
+        let barrier = Barrier::new(THREADS);
+        thread::scope(|scope| {
+            for _ in 0..THREADS {
+                scope.spawn(|_scope| {
+                    let _ = LiveThreadId::current();
+                    barrier.wait();
+                });
+            }
+        })
+        .unwrap();
+        // releasing and reusing ids neither grows nor shrinks the reserved capacity
+        assert_eq!(live::free_list_capacity(), capacity);
+    }
+    live::compact();
+    assert_eq!(live::free_list_capacity(), capacity);
+}
@@ -0,0 +1,31 @@
+#![cfg(feature = "std")]
+
+use crossbeam_utils::thread;
+use threadid::{LiveThreadId, live};
+
+/// The number of threads spawned one after another.
+const ITERATIONS: u64 = 16;
+
+#[test]
+fn reuse_stats() {
+    let _ = LiveThreadId::current();
+    let before = live::stats();
+    assert!(before.fresh >= 1);
+    assert!(before.peak_live >= 1);
+    for _ in 0..ITERATIONS {
+        thread::scope(|scope| {
+            scope
+                .spawn(|_scope| {
+                    let _ = LiveThreadId::current();
+                })
+                .join()
+                .unwrap();
+        })
+        .unwrap();
+    }
+    let after = live::stats();
+    // only the first thread may need a fresh id, every later thread reuses it
+    assert!(after.fresh <= before.fresh + 1);
+    assert!(after.reused >= before.reused + ITERATIONS - 1);
+    assert!(after.peak_live <= before.peak_live + 1);
+}
@@ -0,0 +1,87 @@
+#![cfg(feature = "testing")]
+
+use std::sync::Mutex;
+
+use crossbeam_utils::thread;
+#[cfg(feature = "std")]
+use threadid::LiveThreadId;
+use threadid::UniqueThreadId;
+
+/// Prevents the tests which reset global state from running concurrently.
+static RESET_LOCK: Mutex<()> = Mutex::new(());
+
+#[test]
+fn reset_all() {
+    let _lock = RESET_LOCK.lock().unwrap_or_else(std::sync::PoisonError::into_inner);
+    let check_initial = || {
+        #[cfg(not(all(feature = "nightly", feature = "std")))]
+        assert_eq!(UniqueThreadId::current().to_int(), 1);
+        #[cfg(feature = "std")]
+        assert_eq!(LiveThreadId::current().to_int(), 0);
+    };
+    // SAFETY: No other threads are running
+    unsafe {
+        threadid::reset_all_for_testing();
+    }
+    check_initial();
+    thread::scope(|scope| {
+        scope
+            .spawn(|_scope| {
+                let _ = UniqueThreadId::current();
+                #[cfg(feature = "std")]
+                let _ = LiveThreadId::current();
+            })
+            .join()
+            .unwrap();
+    })
+    .unwrap();
+    // SAFETY: The above thread has died
+    unsafe {
+        threadid::reset_all_for_testing();
+    }
+    check_initial();
+}
+
+#[test]
+#[cfg(feature = "std")]
+fn reset_live_allocator() {
+    let _lock = RESET_LOCK.lock().unwrap_or_else(std::sync::PoisonError::into_inner);
+    thread::scope(|scope| {
+        let _ = scope.spawn(|_scope| LiveThreadId::current()).join().unwrap();
+        // SAFETY: No other threads holding ids are running, including the one above
+        unsafe {
+            threadid::live::reset_allocator();
+        }
+        assert_eq!(LiveThreadId::current().to_int(), 0);
+        let other = scope.spawn(|_scope| LiveThreadId::current()).join().unwrap();
+        assert_eq!(other.to_int(), 1);
+    })
+    .unwrap();
+}
+
+#[test]
+#[cfg(feature = "std")]
+fn reset_runs_exit_callbacks() {
+    use std::sync::{Arc, Mutex};
+    let _lock = RESET_LOCK.lock().unwrap_or_else(std::sync::PoisonError::into_inner);
+    thread::scope(|scope| {
+        scope
+            .spawn(|_scope| {
+                let released = Arc::new(Mutex::new(None));
+                let id = LiveThreadId::current();
+                let callback_released = Arc::clone(&released);
+                assert!(threadid::live::on_thread_exit(move |id| {
+                    *callback_released.lock().unwrap() = Some(id);
+                }));
+                // SAFETY: No other threads holding ids are running
+                unsafe {
+                    threadid::live::reset_allocator();
+                }
+                // the callbacks ran with the id being discarded
+                assert_eq!(*released.lock().unwrap(), Some(id));
+            })
+            .join()
+            .unwrap();
+    })
+    .unwrap();
+}